@@ -0,0 +1,73 @@
+//! Put SPIR-V for the bundled shaders in `OUT_DIR` so it can be embedded in the library.
+//! Every `.vert` and `.frag` file in `shaders` ends up as `<name>.spv`. By default that's
+//! a copy of the SPIR-V checked in under `shaders/spirv`. With the `build-shaders` feature
+//! the GLSL is compiled instead, which is needed after changing a bundled shader. Copy the
+//! results from `OUT_DIR` back into `shaders/spirv` to check them in. Running the tests
+//! with `build-shaders` fails while any checked in SPIR-V differs from what's compiled.
+use std::{env, fs, path};
+
+// Renamed in the manifest so it doesn't share a feature with the runtime compiler.
+#[cfg(feature = "build-shaders")]
+extern crate shaderc_build as shaderc;
+
+#[cfg(feature = "build-shaders")]
+#[path = "src/shader/defines.rs"]
+mod defines;
+#[cfg(feature = "build-shaders")]
+#[path = "src/shader/preprocess.rs"]
+mod preprocess;
+
+fn main() {
+    let out_dir = path::PathBuf::from(env::var("OUT_DIR").unwrap());
+    let shader_dir = path::PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
+        .join("shaders");
+    println!("cargo:rerun-if-changed={}", shader_dir.display());
+
+    let shaders = fs::read_dir(&shader_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|filepath| {
+            let extension = filepath.extension().and_then(|ext| ext.to_str());
+            matches!(extension, Some("vert" | "frag"))
+        });
+
+    for filepath in shaders {
+        let name = filepath.file_name().unwrap().to_str().unwrap();
+        let spirv = spirv(&shader_dir, &filepath, name);
+        fs::write(out_dir.join(format!("{}.spv", name)), spirv).unwrap();
+    }
+}
+
+/// The checked in SPIR-V for the shader.
+#[cfg(not(feature = "build-shaders"))]
+fn spirv(shader_dir: &path::Path, _filepath: &path::Path, name: &str) -> Vec<u8> {
+    let spv = shader_dir.join("spirv").join(format!("{}.spv", name));
+    println!("cargo:rerun-if-changed={}", spv.display());
+    fs::read(&spv).unwrap_or_else(|e| {
+        panic!("No SPIR-V for {} in {}: {}", name, spv.display(), e)
+    })
+}
+
+/// Compile the shader from its GLSL.
+#[cfg(feature = "build-shaders")]
+fn spirv(shader_dir: &path::Path, filepath: &path::Path, name: &str) -> Vec<u8> {
+    use shaderc::{Compiler, ShaderKind};
+
+    println!("cargo:rerun-if-changed={}", filepath.display());
+    println!("cargo:rerun-if-changed=src/shader/defines.rs");
+    println!("cargo:rerun-if-changed=src/shader/preprocess.rs");
+
+    let kind = match filepath.extension().and_then(|ext| ext.to_str()) {
+        Some("vert") => ShaderKind::Vertex,
+        _ => ShaderKind::Fragment,
+    };
+    let mut compiler = Compiler::new().expect("Can't create shader compiler.");
+    let options = preprocess::compile_options(Some(shader_dir))
+        .expect("Can't create shader compile options.");
+
+    let contents = fs::read_to_string(filepath).unwrap();
+    let artifact = compiler
+        .compile_into_spirv(&contents, kind, name, "main", Some(&options))
+        .unwrap_or_else(|e| panic!("Can't compile {}: {}", name, e));
+    artifact.as_binary_u8().to_vec()
+}
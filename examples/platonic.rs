@@ -42,13 +42,26 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     let flat_shaders = shader::load_flat_shaders()?;
     
     let scene = Scene::new()
-        .shaders(&flat_shaders)
+        .shaders(&flat_shaders)?
         .add_light(light1)
         .add_light(light2)
         //.add_light(light3)
-        .geometry(solid);
+        .geometry(solid)?;
 
-    presentation::run("Platonic Solid", scene)?;
+    let config = presentation::RunConfig::new("Platonic Solid")
+        .sample_count(4);
+
+    // Edit the shaders in `shaders/` while running to see the changes.
+    #[cfg(feature = "hot-reload")]
+    {
+        let shader_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders");
+        let watcher =
+            shader::reload::ShaderWatcher::new(shader_dir, "flat.vert", "flat.frag")?;
+        presentation::run_app(config, scene, watcher)?;
+    }
+
+    #[cfg(not(feature = "hot-reload"))]
+    presentation::run(config, scene)?;
 
     Ok(())
 }
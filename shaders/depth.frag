@@ -0,0 +1,26 @@
+#version 450
+
+// Depth view shader. Depth is linearised between the near and far planes, near is white.
+
+layout(location = 0) out vec4 o_Colour;
+
+layout(set = 0, binding = 2) uniform Clip {
+  float u_Near;
+  float u_Far;
+  int u_Orthographic;
+};
+
+void main() {
+  // The vertex shader maps depth to 0..1 so undo that before linearising.
+  float depth = gl_FragCoord.z;
+  float distance;
+  if (u_Orthographic != 0) {
+    distance = u_Near + depth * (u_Far - u_Near);
+  } else {
+    float ndc = 2.0 * depth - 1.0;
+    distance = 2.0 * u_Near * u_Far / (u_Far + u_Near - ndc * (u_Far - u_Near));
+  }
+
+  float shade = 1.0 - (distance - u_Near) / (u_Far - u_Near);
+  o_Colour = vec4(vec3(shade), 1.0);
+}
@@ -1,8 +1,8 @@
 #version 450
 
-// Flat shader.
+// Flat shader. MAX_LIGHTS is defined by the crate when compiling.
 
-const int MAX_LIGHTS = 10;
+#include "light.glsl"
 
 layout(location = 0) in vec4 v_Position;
 layout(location = 1) in vec3 v_Normal;
@@ -10,14 +10,8 @@ layout(location = 2) in vec3 f_Colour;
 
 layout(location = 0) out vec4 o_Colour;
 
-struct Light {
-  mat4 projection;
-  vec4 position;
-  vec4 colour;
-};
-
 layout(set = 0, binding = 2) uniform Lights {
-  Light u_Lights[2];
+  Light u_Lights[MAX_LIGHTS];
 };
 
 layout(set = 0, binding = 3) uniform NumberOfLights {
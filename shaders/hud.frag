@@ -0,0 +1,16 @@
+#version 450
+
+// HUD text. The font atlas holds how much of each texel is covered.
+
+layout(location = 0) in vec2 f_TexCoord;
+layout(location = 1) in vec4 f_Colour;
+
+layout(location = 0) out vec4 o_Colour;
+
+layout(set = 0, binding = 0) uniform texture2D t_Font;
+layout(set = 0, binding = 1) uniform sampler s_Font;
+
+void main() {
+  float coverage = texture(sampler2D(t_Font, s_Font), f_TexCoord).r;
+  o_Colour = vec4(f_Colour.rgb, f_Colour.a * coverage);
+}
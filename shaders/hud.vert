@@ -0,0 +1,15 @@
+#version 450
+
+// HUD text. Positions are already in normalised device coordinates.
+
+layout(location = 0) in vec2 i_Position;
+layout(location = 1) in vec2 i_TexCoord;
+layout(location = 2) in vec4 i_Colour;
+layout(location = 0) out vec2 f_TexCoord;
+layout(location = 1) out vec4 f_Colour;
+
+void main() {
+  f_TexCoord = i_TexCoord;
+  f_Colour = i_Colour;
+  gl_Position = vec4(i_Position, 0.0, 1.0);
+}
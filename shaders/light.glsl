@@ -0,0 +1,12 @@
+// Lighting shared between shaders. Must match `LightRaw` in `src/light.rs`.
+
+#ifndef LIGHT_GLSL
+#define LIGHT_GLSL
+
+struct Light {
+  mat4 projection;
+  vec4 position;
+  vec4 colour;
+};
+
+#endif
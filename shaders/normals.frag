@@ -0,0 +1,11 @@
+#version 450
+
+// Normals debug shader. Each axis maps from -1..1 to 0..1 of a colour channel.
+
+layout(location = 0) in vec3 v_Normal;
+
+layout(location = 0) out vec4 o_Colour;
+
+void main() {
+  o_Colour = vec4(0.5 * normalize(v_Normal) + 0.5, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+// Unlit shader. Just the vertex colour.
+
+layout(location = 0) in vec3 f_Colour;
+
+layout(location = 0) out vec4 o_Colour;
+
+void main() {
+  o_Colour = vec4(f_Colour, 1.0);
+}
@@ -0,0 +1,21 @@
+#version 450
+
+// Unlit shader.
+
+layout(location = 0) in vec3 i_Position;
+layout(location = 2) in vec3 i_Colour;
+layout(location = 0) out vec3 f_Colour;
+
+layout(set = 0, binding = 0) uniform Projection {
+  mat4 u_Camera;
+};
+
+layout(set = 0, binding = 1) uniform Translate {
+  mat4 u_Rotation;
+};
+
+void main() {
+  f_Colour = i_Colour;
+  gl_Position = u_Camera * u_Rotation * vec4(i_Position, 1.0);
+  gl_Position.z = 0.5 * (gl_Position.z + gl_Position.w);
+}
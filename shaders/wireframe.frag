@@ -0,0 +1,19 @@
+#version 450
+
+// Barycentric wireframe shader. Keeps fragments within a pixel or so of an edge.
+
+layout(location = 0) in vec3 v_Barycentric;
+layout(location = 1) in vec3 f_Colour;
+
+layout(location = 0) out vec4 o_Colour;
+
+const float WIDTH = 1.5;
+
+void main() {
+  vec3 pixels = v_Barycentric / fwidth(v_Barycentric);
+  if (min(pixels.x, min(pixels.y, pixels.z)) > WIDTH) {
+    discard;
+  }
+
+  o_Colour = vec4(f_Colour, 1.0);
+}
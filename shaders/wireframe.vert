@@ -0,0 +1,26 @@
+#version 450
+
+// Barycentric wireframe shader. Drawn without an index so every triangle has its own
+// three vertices, which tells the corners apart.
+
+layout(location = 0) in vec3 i_Position;
+layout(location = 2) in vec3 i_Colour;
+layout(location = 0) out vec3 v_Barycentric;
+layout(location = 1) out vec3 f_Colour;
+
+layout(set = 0, binding = 0) uniform Projection {
+  mat4 u_Camera;
+};
+
+layout(set = 0, binding = 1) uniform Translate {
+  mat4 u_Rotation;
+};
+
+const vec3 CORNERS[3] = vec3[3](vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0));
+
+void main() {
+  v_Barycentric = CORNERS[gl_VertexIndex % 3];
+  f_Colour = i_Colour;
+  gl_Position = u_Camera * u_Rotation * vec4(i_Position, 1.0);
+  gl_Position.z = 0.5 * (gl_Position.z + gl_Position.w);
+}
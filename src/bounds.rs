@@ -0,0 +1,85 @@
+//! Bounding volumes of scene geometry. Used to frame the camera around whatever is being
+//! shown.
+use derive_getters::Getters;
+use cgmath::{Point3, EuclideanSpace, MetricSpace, InnerSpace};
+
+/// Axis aligned bounding box.
+#[derive(Debug, Copy, Clone, Getters)]
+pub struct BoundingBox {
+    min: Point3<f32>,
+    max: Point3<f32>,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        BoundingBox { min, max }
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Getters)]
+pub struct BoundingSphere {
+    center: Point3<f32>,
+    radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Point3<f32>, radius: f32) -> Self {
+        BoundingSphere { center, radius }
+    }
+}
+
+/// Box and sphere enclosing a set of points. The sphere is centered on the box and so
+/// isn't the minimal sphere, but it's close enough for framing.
+#[derive(Debug, Copy, Clone, Getters)]
+pub struct Bounds {
+    bbox: BoundingBox,
+    sphere: BoundingSphere,
+}
+
+impl Bounds {
+    /// Compute the bounds of the supplied points. No points yields a unit sphere at the
+    /// origin so there's still something sensible to frame.
+    pub fn from_points(points: &[Point3<f32>]) -> Self {
+        let first = match points.first() {
+            Some(point) => *point,
+            None => return Bounds::default(),
+        };
+
+        let (min, max) = points.iter().fold((first, first), |(min, max), p| {
+            (
+                Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        });
+        let bbox = BoundingBox::new(min, max);
+
+        let center = bbox.center();
+        let radius = points
+            .iter()
+            .map(|p| center.distance(*p))
+            .fold(0f32, f32::max);
+
+        Bounds { bbox, sphere: BoundingSphere::new(center, radius) }
+    }
+
+    /// Radius of the sphere centered on the origin that encloses the geometry in any
+    /// orientation. Shapes are rotated about the origin, not their own center.
+    pub fn rotation_radius(&self) -> f32 {
+        self.sphere.center.to_vec().magnitude() + self.sphere.radius
+    }
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds {
+            bbox: BoundingBox::new(
+                Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0),
+            ),
+            sphere: BoundingSphere::new(Point3::origin(), 1.0),
+        }
+    }
+}
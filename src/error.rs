@@ -0,0 +1,173 @@
+//! Crate wide error type.
+use std::{error, fmt, io};
+
+use crate::input::{BindingsError, RecordingError};
+use crate::presentation::ConfigError;
+use crate::shader::reflect::InterfaceError;
+
+/// Problems with geometry handed to a `Scene`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeometryError {
+    /// No vertices or no indices.
+    Empty,
+    /// The index count isn't a multiple of three so can't be a triangle list.
+    IndexCount(usize),
+    /// An index points past the end of the vertices.
+    IndexOutOfRange { index: u16, vertices: usize },
+    /// Vertices aren't the three packed `Float3`s the pipeline reads.
+    VertexSize(usize),
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeometryError::Empty => write!(f, "Geometry has no triangles."),
+            GeometryError::IndexCount(count) => write!(
+                f, "Index count {} isn't a multiple of three.", count,
+            ),
+            GeometryError::IndexOutOfRange { index, vertices } => write!(
+                f, "Index {} is out of range of the {} vertices.", index, vertices,
+            ),
+            GeometryError::VertexSize(size) => write!(
+                f, "Vertices are {} bytes rather than nine floats.", size,
+            ),
+        }
+    }
+}
+
+impl error::Error for GeometryError {}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The shader compiler couldn't be created.
+    #[cfg(feature = "runtime-shaders")]
+    CompilerUnavailable,
+    /// GLSL failed to compile.
+    #[cfg(feature = "runtime-shaders")]
+    Shader(shaderc::Error),
+    Io(io::Error),
+    /// Compiled shader bytes aren't valid SPIR-V.
+    Spirv(io::Error),
+    /// Shader inputs or uniform blocks don't match what's bound.
+    Interface(InterfaceError),
+    /// No custom uniform at the binding or the data doesn't fit.
+    Uniform(u32),
+    /// No video adapter matched the power preference.
+    AdapterUnavailable(wgpu::PowerPreference),
+    /// The adapter couldn't provide a device.
+    DeviceUnavailable,
+    Window(winit::error::OsError),
+    /// Can't watch shader files for changes.
+    #[cfg(feature = "hot-reload")]
+    Watch(notify::Error),
+    Config(ConfigError),
+    Geometry(GeometryError),
+    Bindings(BindingsError),
+    Recording(RecordingError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "runtime-shaders")]
+            Error::CompilerUnavailable => write!(f, "Can't create the shader compiler."),
+            #[cfg(feature = "runtime-shaders")]
+            Error::Shader(e) => write!(f, "Shader compilation failed: {}", e),
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::Spirv(e) => write!(f, "Invalid SPIR-V: {}", e),
+            Error::Interface(e) => write!(f, "Shader interface mismatch: {}", e),
+            Error::Uniform(binding) => write!(
+                f, "No custom uniform at binding {} large enough for the data.", binding,
+            ),
+            Error::AdapterUnavailable(preference) => write!(
+                f, "No video adapter available for power preference {:?}.", preference,
+            ),
+            Error::DeviceUnavailable => {
+                write!(f, "Video adapter couldn't provide a device.")
+            },
+            Error::Window(e) => write!(f, "Can't create window: {}", e),
+            #[cfg(feature = "hot-reload")]
+            Error::Watch(e) => write!(f, "Can't watch shaders: {}", e),
+            Error::Config(e) => write!(f, "Invalid configuration: {}", e),
+            Error::Geometry(e) => write!(f, "Invalid geometry: {}", e),
+            Error::Bindings(e) => write!(f, "Invalid key bindings: {}", e),
+            Error::Recording(e) => write!(f, "Invalid input recording: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "runtime-shaders")]
+            Error::Shader(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Spirv(e) => Some(e),
+            Error::Interface(e) => Some(e),
+            Error::Window(e) => Some(e),
+            #[cfg(feature = "hot-reload")]
+            Error::Watch(e) => Some(e),
+            Error::Config(e) => Some(e),
+            Error::Geometry(e) => Some(e),
+            Error::Bindings(e) => Some(e),
+            Error::Recording(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "runtime-shaders")]
+impl From<shaderc::Error> for Error {
+    fn from(e: shaderc::Error) -> Self {
+        Error::Shader(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<winit::error::OsError> for Error {
+    fn from(e: winit::error::OsError) -> Self {
+        Error::Window(e)
+    }
+}
+
+#[cfg(feature = "hot-reload")]
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        Error::Watch(e)
+    }
+}
+
+impl From<InterfaceError> for Error {
+    fn from(e: InterfaceError) -> Self {
+        Error::Interface(e)
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<GeometryError> for Error {
+    fn from(e: GeometryError) -> Self {
+        Error::Geometry(e)
+    }
+}
+
+impl From<BindingsError> for Error {
+    fn from(e: BindingsError) -> Self {
+        Error::Bindings(e)
+    }
+}
+
+impl From<RecordingError> for Error {
+    fn from(e: RecordingError) -> Self {
+        Error::Recording(e)
+    }
+}
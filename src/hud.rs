@@ -0,0 +1,416 @@
+//! Text drawn over the scene, such as frame timing and where the camera is.
+//!
+//! Lines are laid out from the top left of the frame in a bundled bitmap font over a dark
+//! backdrop. They're drawn in a second pass straight into the frame once the scene is done,
+//! so they're never multisampled or hidden by the geometry.
+use std::io;
+
+use crate::shader::{self, CompiledShaders};
+use crate::Error;
+
+mod font;
+
+/// Screen pixels along each side of a font pixel.
+const SCALE: f32 = 2.0;
+/// Space between the window edges and the backdrop, in screen pixels.
+const MARGIN: f32 = 8.0;
+/// Space between the backdrop edges and the text, in screen pixels.
+const PADDING: f32 = 6.0;
+/// Font pixels between glyphs and between lines.
+const GLYPH_GAP: u32 = 1;
+const LINE_GAP: u32 = 3;
+
+const TEXT_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKDROP_COLOUR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+/// Glyphs along each row of the font atlas.
+const ATLAS_COLUMNS: u32 = 16;
+/// The atlas cell after the last glyph is solid for drawing the backdrop.
+const SOLID: u32 = font::COUNT;
+/// Rows of texels copied from a buffer into a texture must start this many bytes apart.
+const ROW_ALIGNMENT: u32 = 256;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+struct GlyphVertex {
+    /// Normalised device coordinates.
+    position: [f32; 2],
+    tex_coord: [f32; 2],
+    colour: [f32; 4],
+}
+
+/// Width and height of the font atlas in texels.
+fn atlas_size() -> (u32, u32) {
+    let rows = (SOLID + ATLAS_COLUMNS) / ATLAS_COLUMNS;
+    (ATLAS_COLUMNS * font::WIDTH, rows * font::HEIGHT)
+}
+
+/// Top left texel of the atlas cell for glyph `index`.
+fn cell(index: u32) -> (u32, u32) {
+    ((index % ATLAS_COLUMNS) * font::WIDTH, (index / ATLAS_COLUMNS) * font::HEIGHT)
+}
+
+/// Texture coordinates of the left, top, right and bottom of a cell.
+fn cell_coords(index: u32) -> [f32; 4] {
+    let (width, height) = atlas_size();
+    let (left, top) = cell(index);
+    [
+        left as f32 / width as f32,
+        top as f32 / height as f32,
+        (left + font::WIDTH) as f32 / width as f32,
+        (top + font::HEIGHT) as f32 / height as f32,
+    ]
+}
+
+/// The font atlas with a byte per texel, set where a glyph covers it, and rows `pitch`
+/// bytes apart.
+fn atlas_texels(pitch: u32) -> Vec<u8> {
+    let (_, height) = atlas_size();
+    let mut texels = vec![0u8; (pitch * height) as usize];
+
+    for index in 0..=SOLID {
+        let (left, top) = cell(index);
+        for y in 0..font::HEIGHT {
+            for x in 0..font::WIDTH {
+                if index == SOLID || font::pixel(index, x, y) {
+                    texels[((top + y) * pitch + left + x) as usize] = 255;
+                }
+            }
+        }
+    }
+
+    texels
+}
+
+/// Two triangles covering the left, top, right and bottom of `rect`, in pixels from the top
+/// left of a `screen` sized frame.
+fn quad(
+    screen: (f32, f32), rect: [f32; 4], tex: [f32; 4], colour: [f32; 4],
+) -> [GlyphVertex; 6] {
+    let x = |px: f32| px / screen.0 * 2.0 - 1.0;
+    let y = |px: f32| 1.0 - px / screen.1 * 2.0;
+    let corner = |h: usize, v: usize| GlyphVertex {
+        position: [x(rect[h]), y(rect[v])],
+        tex_coord: [tex[h], tex[v]],
+        colour,
+    };
+    let (top_left, top_right) = (corner(0, 1), corner(2, 1));
+    let (bottom_left, bottom_right) = (corner(0, 3), corner(2, 3));
+
+    [top_left, bottom_left, top_right, top_right, bottom_left, bottom_right]
+}
+
+/// Vertices for the backdrop then each glyph of the lines. Nothing when there's no text.
+fn layout(lines: &[String], screen: (f32, f32)) -> Vec<GlyphVertex> {
+    let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    if columns == 0 {
+        return Vec::new();
+    }
+
+    let glyph = (font::WIDTH as f32 * SCALE, font::HEIGHT as f32 * SCALE);
+    let advance = (font::WIDTH + GLYPH_GAP) as f32 * SCALE;
+    let line_height = (font::HEIGHT + LINE_GAP) as f32 * SCALE;
+
+    let width = (columns - 1) as f32 * advance + glyph.0;
+    let height = (lines.len() - 1) as f32 * line_height + glyph.1;
+    let (right, bottom) = (MARGIN + width + 2.0 * PADDING, MARGIN + height + 2.0 * PADDING);
+    let backdrop = [MARGIN, MARGIN, right, bottom];
+    let mut vertices = quad(screen, backdrop, cell_coords(SOLID), BACKDROP_COLOUR).to_vec();
+
+    for (row, line) in lines.iter().enumerate() {
+        let top = MARGIN + PADDING + row as f32 * line_height;
+        for (column, c) in line.chars().enumerate().filter(|(_, c)| *c != ' ') {
+            let left = MARGIN + PADDING + column as f32 * advance;
+            let rect = [left, top, left + glyph.0, top + glyph.1];
+            let tex = cell_coords(font::index(c));
+            vertices.extend_from_slice(&quad(screen, rect, tex, TEXT_COLOUR));
+        }
+    }
+
+    vertices
+}
+
+/// Upload the font atlas as a texture.
+fn upload_atlas(device: &mut wgpu::Device) -> (wgpu::Texture, wgpu::TextureView) {
+    let (width, height) = atlas_size();
+    let pitch = (width + ROW_ALIGNMENT - 1) / ROW_ALIGNMENT * ROW_ALIGNMENT;
+    let extent = wgpu::Extent3d { width, height, depth: 1 };
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: extent,
+        array_layer_count: 1,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::R8Unorm,
+        usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+    });
+    let view = texture.create_default_view();
+
+    let texels = atlas_texels(pitch);
+    let texel_buf = device
+        .create_buffer_mapped(texels.len(), wgpu::BufferUsage::COPY_SRC)
+        .fill_from_slice(&texels);
+
+    let mut encoder = device.create_command_encoder(
+        &wgpu::CommandEncoderDescriptor { todo: 0 }
+    );
+    encoder.copy_buffer_to_texture(
+        wgpu::BufferCopyView {
+            buffer: &texel_buf,
+            offset: 0,
+            row_pitch: pitch,
+            image_height: height,
+        },
+        wgpu::TextureCopyView {
+            texture: &texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: wgpu::Origin3d { x: 0.0, y: 0.0, z: 0.0 },
+        },
+        extent,
+    );
+    device.get_queue().submit(&[encoder.finish()]);
+
+    (texture, view)
+}
+
+/// Draws lines of text over the top left of each frame.
+pub struct Hud {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    /// Only held so the bind group stays valid.
+    _atlas: (wgpu::Texture, wgpu::TextureView, wgpu::Sampler),
+    /// Frame width and height in pixels.
+    screen: (f32, f32),
+}
+
+impl Hud {
+    /// Upload the font and build the pipeline for frames as described by `desc`.
+    pub fn new(
+        desc: &wgpu::SwapChainDescriptor, device: &mut wgpu::Device,
+    ) -> Result<Self, Error> {
+        let shaders = shader::hud_shaders();
+        let vert = wgpu::read_spirv(io::Cursor::new(shaders.vertex()))
+            .map_err(Error::Spirv)?;
+        let frag = wgpu::read_spirv(io::Cursor::new(shaders.fragment()))
+            .map_err(Error::Spirv)?;
+
+        let (texture, view) = upload_atlas(device);
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            compare_function: wgpu::CompareFunction::Always,
+        });
+
+        let bg_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            bindings: &[
+                wgpu::BindGroupLayoutBinding {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture,
+                },
+                wgpu::BindGroupLayoutBinding {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bg_layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&bg_layout], }
+        );
+
+        let m_vert = device.create_shader_module(&vert);
+        let m_frag = device.create_shader_module(&frag);
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: &pipeline_layout,
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &m_vert,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &m_frag,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                depth_bias: 0,
+                depth_bias_slope_scale: 0.0,
+                depth_bias_clamp: 0.0,
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            color_states: &[wgpu::ColorStateDescriptor {
+                format: desc.format,
+                color_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            depth_stencil_state: None,
+            index_format: wgpu::IndexFormat::Uint16,
+            vertex_buffers: &[wgpu::VertexBufferDescriptor {
+                stride: std::mem::size_of::<GlyphVertex>() as u64,
+                step_mode: wgpu::InputStepMode::Vertex,
+                attributes: &[
+                    wgpu::VertexAttributeDescriptor {
+                        format: wgpu::VertexFormat::Float2,
+                        offset: 0,
+                        shader_location: 0,
+                    },
+                    wgpu::VertexAttributeDescriptor {
+                        format: wgpu::VertexFormat::Float2,
+                        offset: 4 * 2,
+                        shader_location: 1,
+                    },
+                    wgpu::VertexAttributeDescriptor {
+                        format: wgpu::VertexFormat::Float4,
+                        offset: 4 * 4,
+                        shader_location: 2,
+                    },
+                ],
+            }],
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        Ok(Hud {
+            pipeline,
+            bind_group,
+            _atlas: (texture, view, sampler),
+            screen: (desc.width as f32, desc.height as f32),
+        })
+    }
+
+    /// Draw the lines over what's already in the frame. Characters outside printable ASCII
+    /// are drawn as `?`.
+    pub fn render(
+        &mut self,
+        lines: &[String],
+        frame: &wgpu::SwapChainOutput,
+        device: &mut wgpu::Device,
+    ) {
+        let vertices = layout(lines, self.screen);
+        if vertices.is_empty() {
+            return;
+        }
+
+        let vertex_buf = device
+            .create_buffer_mapped(vertices.len(), wgpu::BufferUsage::VERTEX)
+            .fill_from_slice(&vertices);
+
+        let mut encoder = device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor { todo: 0 }
+        );
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &frame.view,
+                    resolve_target: None,
+                    load_op: wgpu::LoadOp::Load,
+                    store_op: wgpu::StoreOp::Store,
+                    clear_color: wgpu::Color::BLACK,
+                }],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.set_vertex_buffers(0, &[(&vertex_buf, 0)]);
+            rpass.draw(0..vertices.len() as u32, 0..1);
+        }
+
+        device.get_queue().submit(&[encoder.finish()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: (f32, f32) = (800.0, 600.0);
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn no_text_draws_nothing() {
+        assert!(layout(&[], SCREEN).is_empty());
+        assert!(layout(&lines(&["", ""]), SCREEN).is_empty());
+    }
+
+    #[test]
+    fn a_quad_for_the_backdrop_and_each_glyph() {
+        let vertices = layout(&lines(&["ab c", "", "d"]), SCREEN);
+        assert_eq!(vertices.len(), 6 * (1 + 4));
+        assert!(vertices[..6].iter().all(|v| v.colour == BACKDROP_COLOUR));
+        assert!(vertices[6..].iter().all(|v| v.colour == TEXT_COLOUR));
+    }
+
+    #[test]
+    fn glyphs_sit_on_the_backdrop() {
+        let text = lines(&["Camera 0.00, -4.00, 4.00", "Frame 16.7 ms"]);
+        let vertices = layout(&text, SCREEN);
+        let (xs, ys): (Vec<f32>, Vec<f32>) = vertices[..6]
+            .iter()
+            .map(|v| (v.position[0], v.position[1]))
+            .unzip();
+        let min = |v: &[f32]| v.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = |v: &[f32]| v.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+
+        assert!(min(&xs) > -1.0 && max(&ys) < 1.0);
+        for vertex in vertices[6..].iter() {
+            let [x, y] = vertex.position;
+            assert!(x > min(&xs) && x < max(&xs));
+            assert!(y > min(&ys) && y < max(&ys));
+        }
+    }
+
+    #[test]
+    fn unknown_characters_draw_as_question_marks() {
+        assert_eq!(font::index('é'), font::index('?'));
+        assert_eq!(font::index('\n'), font::index('?'));
+        assert_ne!(font::index('A'), font::index('?'));
+    }
+
+    #[test]
+    fn atlas_holds_each_glyph_in_its_cell() {
+        let (width, _) = atlas_size();
+        let texels = atlas_texels(width);
+        let texel = |index: u32, x: u32, y: u32| {
+            let (left, top) = cell(index);
+            texels[((top + y) * width + left + x) as usize]
+        };
+
+        // The bar of the T across the top and its stem down the middle.
+        let t = font::index('T');
+        assert!((0..font::WIDTH).all(|x| texel(t, x, 0) == 255));
+        assert!((1..font::HEIGHT).all(|y| texel(t, 2, y) == 255 && texel(t, 0, y) == 0));
+        assert!(texel(font::index(' '), 2, 3) == 0);
+        assert!(texel(SOLID, 4, 6) == 255);
+    }
+}
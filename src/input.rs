@@ -1,38 +1,40 @@
 //! Input processing. Using the command pattern but instead of returning an `action`, will
 //! return a transform to be applied.
+//!
+//! Key and mouse button events only toggle the held actions or queue up triggers. Mouse
+//! motion and the wheel nudge actions along by an amount. The transform itself is computed
+//! once per frame from the held actions, the time elapsed since the last frame and any
+//! nudges.
 use std::collections::HashMap;
-use std::ops::Neg;
+use std::mem;
+use std::ops::Add;
+use std::time::Duration;
 
-use winit::event::{KeyboardInput, VirtualKeyCode, ElementState};
 use cgmath::{Vector3, Zero, Rad, Deg};
 
+use crate::keyboard::{Key, KeyEvent, KeyState};
+
+mod file;
+mod mouse;
+mod record;
+
+pub use crate::keyboard::Modifiers;
+pub use file::BindingsError;
+pub use record::{InputEvent, Frame, Recording, RecordingError};
+pub use mouse::{
+    Axis, AxisBinding, MouseButton, Position, ScrollDelta, handle_mouse_button,
+    handle_cursor, handle_wheel,
+};
+
 pub type Camera = Vector3<f32>;
 pub type RotY = Rad<f32>;
 pub type RotX = Rad<f32>;
+pub type RotZ = Rad<f32>;
+pub type Roll = Rad<f32>;
 
-static SET_CMPX: u16 = 0b0000_0000_0000_0001;
-static SET_CMPY: u16 = 0b0000_0000_0000_0010;
-static SET_CMPZ: u16 = 0b0000_0000_0000_0100;
-static SET_CMNX: u16 = 0b0000_0000_0001_0000;
-static SET_CMNY: u16 = 0b0000_0000_0010_0000;
-static SET_CMNZ: u16 = 0b0000_0000_0100_0000;
-static SET_RSPX: u16 = 0b0000_0001_0000_0000;
-static SET_RSPY: u16 = 0b0000_0010_0000_0000;
-static SET_RSNX: u16 = 0b0000_0100_0000_0000;
-static SET_RSNY: u16 = 0b0000_1000_0000_0000;
-
-static MSK_CMPX: u16 = 0b1111_1111_1111_1110;
-static MSK_CMPY: u16 = 0b1111_1111_1111_1101;
-static MSK_CMPZ: u16 = 0b1111_1111_1111_1011;
-static MSK_CMNX: u16 = 0b1111_1111_1110_1111;
-static MSK_CMNY: u16 = 0b1111_1111_1101_1111;
-static MSK_CMNZ: u16 = 0b1111_1111_1011_1111;
-static MSK_RSPX: u16 = 0b1111_1110_1111_1111;
-static MSK_RSPY: u16 = 0b1111_1101_1111_1111;
-static MSK_RSNX: u16 = 0b1111_1011_1111_1111;
-static MSK_RSNY: u16 = 0b1111_0111_1111_1111;
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// Something bound input can do. The built in actions drive the camera and scene. `Custom`
+/// actions are registered with `Bindings::register` and handled by the application.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Action {
     CameraMovePX,
     CameraMovePY,
@@ -40,155 +42,992 @@ pub enum Action {
     CameraMoveNX,
     CameraMoveNY,
     CameraMoveNZ,
+    /// Roll the camera around the direction it's looking in.
+    CameraRollP,
+    CameraRollN,
     RotateShapePX,
     RotateShapePY,
+    RotateShapePZ,
     RotateShapeNX,
     RotateShapeNY,
+    RotateShapeNZ,
+    ToggleProjection,
+    ResetView,
+    CycleShading,
+    /// Spin the scene on its own until there's input. See `presentation::Turntable`.
+    ToggleTurntable,
+    /// Show or hide the text overlay with frame timing, camera and mesh details.
+    ToggleHud,
+    /// Select the face under the cursor, or clear the selection when there's none.
+    SelectFace,
+    Custom(u16),
 }
 
-impl Action {
-    pub fn bitset(&self) -> u16 {
-        match self {
-            Action::CameraMovePX =>  SET_CMPX,
-            Action::CameraMovePY =>  SET_CMPY,
-            Action::CameraMovePZ =>  SET_CMPZ,
-            Action::CameraMoveNX =>  SET_CMNX,
-            Action::CameraMoveNY =>  SET_CMNY,
-            Action::CameraMoveNZ =>  SET_CMNZ,
-            Action::RotateShapePX => SET_RSPX,
-            Action::RotateShapePY => SET_RSPY,
-            Action::RotateShapeNX => SET_RSNX,
-            Action::RotateShapeNY => SET_RSNY,
-        }
-    }
+/// How an action responds to its binding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ActionKind {
+    /// On for as long as the binding is held.
+    Held,
+    /// Fires once each time the binding is pressed.
+    Trigger,
+}
 
-    pub fn bitmask(&self) -> u16 {
-        match self {
-            Action::CameraMovePX =>  MSK_CMPX,
-            Action::CameraMovePY =>  MSK_CMPY,
-            Action::CameraMovePZ =>  MSK_CMPZ,
-            Action::CameraMoveNX =>  MSK_CMNX,
-            Action::CameraMoveNY =>  MSK_CMNY,
-            Action::CameraMoveNZ =>  MSK_CMNZ,
-            Action::RotateShapePX => MSK_RSPX,
-            Action::RotateShapePY => MSK_RSPY,
-            Action::RotateShapeNX => MSK_RSNX,
-            Action::RotateShapeNY => MSK_RSNY,
-        }
+/// Built in actions with their names in bindings files.
+const BUILT_IN: [(Action, &str, ActionKind); 20] = [
+    (Action::CameraMovePX, "CameraMovePX", ActionKind::Held),
+    (Action::CameraMovePY, "CameraMovePY", ActionKind::Held),
+    (Action::CameraMovePZ, "CameraMovePZ", ActionKind::Held),
+    (Action::CameraMoveNX, "CameraMoveNX", ActionKind::Held),
+    (Action::CameraMoveNY, "CameraMoveNY", ActionKind::Held),
+    (Action::CameraMoveNZ, "CameraMoveNZ", ActionKind::Held),
+    (Action::CameraRollP, "CameraRollP", ActionKind::Held),
+    (Action::CameraRollN, "CameraRollN", ActionKind::Held),
+    (Action::RotateShapePX, "RotateShapePX", ActionKind::Held),
+    (Action::RotateShapePY, "RotateShapePY", ActionKind::Held),
+    (Action::RotateShapePZ, "RotateShapePZ", ActionKind::Held),
+    (Action::RotateShapeNX, "RotateShapeNX", ActionKind::Held),
+    (Action::RotateShapeNY, "RotateShapeNY", ActionKind::Held),
+    (Action::RotateShapeNZ, "RotateShapeNZ", ActionKind::Held),
+    (Action::ToggleProjection, "ToggleProjection", ActionKind::Trigger),
+    (Action::ResetView, "ResetView", ActionKind::Trigger),
+    (Action::CycleShading, "CycleShading", ActionKind::Trigger),
+    (Action::ToggleTurntable, "ToggleTurntable", ActionKind::Trigger),
+    (Action::ToggleHud, "ToggleHud", ActionKind::Trigger),
+    (Action::SelectFace, "SelectFace", ActionKind::Trigger),
+];
+
+impl Action {
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Action::Custom(_))
     }
 }
 
+/// Held actions along with the triggers fired but not yet taken. The camera and rotation
+/// increments are worked out from the held actions.
+///
+/// Opposing actions held at the same time, such as `CameraMovePX` and `CameraMoveNX`,
+/// cancel out so nothing moves along that axis until one of them is let go. Neither
+/// direction is favoured and actions along other axes are unaffected.
 pub trait ActionState {
     fn on(&mut self, action: Action);
     fn off(&mut self, action: Action);
-    fn camera_increment(&self, increment: f32) -> Camera;
-    fn x_rotation_increment(&self, increment: f32) -> Rad<f32>;
-    fn y_rotation_increment(&self, increment: f32) -> Rad<f32>;
+    fn is_on(&self, action: Action) -> bool;
+
+    /// Nothing is held. Untaken triggers don't count.
+    fn is_idle(&self) -> bool;
+
+    /// Fire a one shot action.
+    fn trigger(&mut self, action: Action);
+
+    /// Triggers fired since the last take, in the order they fired.
+    fn take_triggered(&mut self) -> Vec<Action>;
+
+    /// Every held action.
+    fn held(&self) -> Vec<Action>;
+
+    /// Push an action along by an amount for the next frame only. Amounts for the same
+    /// action add up.
+    fn nudge(&mut self, action: Action, amount: f32);
+
+    /// Nudges since the last take.
+    fn take_nudged(&mut self) -> Vec<(Action, f32)>;
+
+    fn camera_increment(&self, increment: f32) -> Camera {
+        Camera::new(
+            increment * direction(self, Action::CameraMovePX, Action::CameraMoveNX),
+            increment * direction(self, Action::CameraMovePY, Action::CameraMoveNY),
+            increment * direction(self, Action::CameraMovePZ, Action::CameraMoveNZ),
+        )
+    }
+
+    fn x_rotation_increment(&self, increment: f32) -> RotX {
+        let sign = direction(self, Action::RotateShapePX, Action::RotateShapeNX);
+        Deg(increment * sign).into()
+    }
+
+    fn y_rotation_increment(&self, increment: f32) -> RotY {
+        let sign = direction(self, Action::RotateShapePY, Action::RotateShapeNY);
+        Deg(increment * sign).into()
+    }
+
+    fn z_rotation_increment(&self, increment: f32) -> RotZ {
+        let sign = direction(self, Action::RotateShapePZ, Action::RotateShapeNZ);
+        Deg(increment * sign).into()
+    }
+
+    fn camera_roll_increment(&self, increment: f32) -> Roll {
+        let sign = direction(self, Action::CameraRollP, Action::CameraRollN);
+        Deg(increment * sign).into()
+    }
+}
+
+/// 1 when only the positive action is held, -1 when only the negative one is and 0 when
+/// both or neither are.
+fn direction<T: ActionState + ?Sized>(
+    state: &T, positive: Action, negative: Action,
+) -> f32 {
+    match (state.is_on(positive), state.is_on(negative)) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => 0.0,
+    }
+}
+
+/// Any number of held actions and a queue of fired triggers. Held actions count how often
+/// they've been switched on, so an action held by two buttons stays on until both let go.
+#[derive(Debug, Clone, Default)]
+pub struct ActionSet {
+    held: HashMap<Action, u32>,
+    triggered: Vec<Action>,
+    nudged: Vec<(Action, f32)>,
+}
+
+impl ActionSet {
+    pub fn new() -> Self {
+        ActionSet::default()
+    }
 }
 
-impl ActionState for u16 {
+impl ActionState for ActionSet {
     fn on(&mut self, action: Action) {
-        *self |= action.bitset();
+        *self.held.entry(action).or_insert(0) += 1;
     }
 
     fn off(&mut self, action: Action) {
-        *self &= action.bitmask();
+        match self.held.get_mut(&action) {
+            Some(count) if *count > 1 => *count -= 1,
+            Some(_) => {
+                self.held.remove(&action);
+            },
+            None => (),
+        }
     }
 
-    fn camera_increment(&self, increment: f32) -> Camera {
-        let mut camera = Camera::zero();
+    fn is_on(&self, action: Action) -> bool {
+        self.held.contains_key(&action)
+    }
 
-        if *self & SET_CMPX > 0 { camera.x = increment; }
-        if *self & SET_CMNX > 0 { camera.x = increment.neg(); }
-        if *self & SET_CMPY > 0 { camera.y = increment; }
-        if *self & SET_CMNY > 0 { camera.y = increment.neg(); }
-        if *self & SET_CMPZ > 0 { camera.z = increment; }
-        if *self & SET_CMNZ > 0 { camera.z = increment.neg(); }
+    fn is_idle(&self) -> bool {
+        self.held.is_empty()
+    }
 
-        camera
+    fn trigger(&mut self, action: Action) {
+        self.triggered.push(action);
     }
 
-    fn x_rotation_increment(&self, increment: f32) -> RotX {
-        if *self & SET_RSPX > 0 { return Deg(increment).into() }
-        if *self & SET_RSNX > 0 { return Deg(increment.neg()).into() }
+    fn take_triggered(&mut self) -> Vec<Action> {
+        mem::take(&mut self.triggered)
+    }
 
-        Rad(0f32)
+    fn held(&self) -> Vec<Action> {
+        let mut held: Vec<Action> = self.held.keys().copied().collect();
+        held.sort();
+        held
     }
 
-    fn y_rotation_increment(&self, increment: f32) -> RotY {
-        if *self & SET_RSPY > 0 { return Deg(increment).into() }
-        if *self & SET_RSNY > 0 { return Deg(increment.neg()).into() }
+    fn nudge(&mut self, action: Action, amount: f32) {
+        match self.nudged.iter_mut().find(|(nudged, _)| *nudged == action) {
+            Some((_, total)) => *total += amount,
+            None => self.nudged.push((action, amount)),
+        }
+    }
+
+    fn take_nudged(&mut self) -> Vec<(Action, f32)> {
+        mem::take(&mut self.nudged)
+    }
+}
+
+/// Anything that's pressed and released.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Button {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+impl From<Key> for Button {
+    fn from(key: Key) -> Self {
+        Button::Key(key)
+    }
+}
+
+impl From<MouseButton> for Button {
+    fn from(button: MouseButton) -> Self {
+        Button::Mouse(button)
+    }
+}
+
+/// A key or mouse button pressed while exactly these modifiers are held. A bare key or
+/// button converts into a chord without modifiers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub button: Button,
+    pub modifiers: Modifiers,
+}
+
+impl Chord {
+    pub fn new<B: Into<Button>>(button: B, modifiers: Modifiers) -> Self {
+        Chord { button: button.into(), modifiers }
+    }
+}
+
+impl From<Key> for Chord {
+    fn from(key: Key) -> Self {
+        Chord::new(key, Modifiers::NONE)
+    }
+}
+
+impl From<MouseButton> for Chord {
+    fn from(button: MouseButton) -> Self {
+        Chord::new(button, Modifiers::NONE)
+    }
+}
 
-        Rad(0f32)
+impl From<Button> for Chord {
+    fn from(button: Button) -> Self {
+        Chord::new(button, Modifiers::NONE)
     }
 }
 
-/// Which keypresses carry out which which actions and by how much.
+/// Which keypresses, mouse buttons and mouse axes carry out which actions and how fast.
+///
+/// The camera speed is in units per second and the rotation and roll speeds are in degrees
+/// per second. Can be read from and saved to a TOML file.
+#[derive(Debug, Clone)]
 pub struct Bindings {
-    bindings: HashMap<VirtualKeyCode, Action>,
-    camera_increment: f32,
-    x_rotation_increment: f32,
-    y_rotation_increment: f32,
+    bindings: HashMap<Chord, Action>,
+    axes: Vec<AxisBinding>,
+    /// Names and kinds of the registered actions, indexed by `Action::Custom`.
+    custom: Vec<(String, ActionKind)>,
+    camera_speed: f32,
+    x_rotation_speed: f32,
+    y_rotation_speed: f32,
+    z_rotation_speed: f32,
+    camera_roll_speed: f32,
 }
 
 impl Bindings {
     pub fn new(
-        camera_increment: f32, x_rotation_increment: f32, y_rotation_increment: f32,
+        camera_speed: f32,
+        x_rotation_speed: f32,
+        y_rotation_speed: f32,
+        z_rotation_speed: f32,
+        camera_roll_speed: f32,
     ) -> Self {
         Bindings {
             bindings: HashMap::new(),
-            camera_increment,
-            x_rotation_increment,
-            y_rotation_increment,
+            axes: Vec::new(),
+            custom: Vec::new(),
+            camera_speed,
+            x_rotation_speed,
+            y_rotation_speed,
+            z_rotation_speed,
+            camera_roll_speed,
+        }
+    }
+
+    /// Register an application action so it can be bound. Registering a name again returns
+    /// the same action with its kind updated. Fails if the name is taken by a built in
+    /// action.
+    pub fn register(
+        &mut self, name: &str, kind: ActionKind,
+    ) -> Result<Action, BindingsError> {
+        if BUILT_IN.iter().any(|(_, built_in, _)| *built_in == name) {
+            return Err(BindingsError::ReservedName(name.to_owned()));
+        }
+
+        match self.custom.iter().position(|(custom, _)| custom == name) {
+            Some(index) => {
+                self.custom[index].1 = kind;
+                Ok(Action::Custom(index as u16))
+            },
+            None => {
+                self.custom.push((name.to_owned(), kind));
+                Ok(Action::Custom((self.custom.len() - 1) as u16))
+            },
+        }
+    }
+
+    /// Built in actions are looked up first, then the registered ones.
+    pub fn action_named(&self, name: &str) -> Option<Action> {
+        BUILT_IN
+            .iter()
+            .find(|(_, built_in, _)| *built_in == name)
+            .map(|(action, _, _)| *action)
+            .or_else(|| {
+                self.custom
+                    .iter()
+                    .position(|(custom, _)| custom == name)
+                    .map(|index| Action::Custom(index as u16))
+            })
+    }
+
+    /// `None` for custom actions that weren't registered here.
+    pub fn action_name(&self, action: Action) -> Option<&str> {
+        match action {
+            Action::Custom(index) => self.custom
+                .get(index as usize)
+                .map(|(name, _)| name.as_str()),
+            _ => BUILT_IN
+                .iter()
+                .find(|(built_in, _, _)| *built_in == action)
+                .map(|(_, name, _)| *name),
+        }
+    }
+
+    /// Unregistered custom actions are treated as held.
+    pub fn kind(&self, action: Action) -> ActionKind {
+        match action {
+            Action::Custom(index) => self.custom
+                .get(index as usize)
+                .map(|(_, kind)| *kind)
+                .unwrap_or(ActionKind::Held),
+            _ => BUILT_IN
+                .iter()
+                .find(|(built_in, _, _)| *built_in == action)
+                .map(|(_, _, kind)| *kind)
+                .unwrap_or(ActionKind::Held),
         }
     }
 
-    pub fn bind(&mut self, vkc: VirtualKeyCode, action: Action) -> Option<Action> {
-        self.bindings.insert(vkc, action)
+    /// Bind a key, mouse button or chord. Returns the action it was bound to before.
+    pub fn bind<C: Into<Chord>>(&mut self, chord: C, action: Action) -> Option<Action> {
+        self.bindings.insert(chord.into(), action)
+    }
+
+    pub fn unbind<C: Into<Chord>>(&mut self, chord: C) -> Option<Action> {
+        self.bindings.remove(&chord.into())
+    }
+
+    /// The action for a key or button with the given modifiers held. A chord bound with
+    /// exactly those modifiers wins, otherwise the bare key or button is used so holding a
+    /// modifier doesn't block those that have no chords.
+    pub fn resolve(&self, button: Button, modifiers: Modifiers) -> Option<Action> {
+        self.bindings
+            .get(&Chord::new(button, modifiers))
+            .or_else(|| self.bindings.get(&Chord::from(button)))
+            .copied()
+    }
+
+    /// Bind mouse motion or the wheel. Any earlier binding of the same axis, modifiers and
+    /// button is replaced.
+    pub fn bind_axis(&mut self, binding: AxisBinding) {
+        let key = (binding.axis, binding.modifiers, binding.button);
+        self.axes.retain(|b| (b.axis, b.modifiers, b.button) != key);
+        self.axes.push(binding);
     }
 
-    pub fn unbind(&mut self, vkc: &VirtualKeyCode) -> Option<Action> {
-        self.bindings.remove(vkc)
+    /// Remove every binding of an axis.
+    pub fn unbind_axis(&mut self, axis: Axis) {
+        self.axes.retain(|b| b.axis != axis);
+    }
+
+    pub fn axes(&self) -> &[AxisBinding] {
+        &self.axes
     }
 }
 
 impl Default for Bindings {
     fn default() -> Self {
-        let mut bindings = Bindings::new(0.1f32, 0.5f32, 0.5f32);
-        bindings.bind(VirtualKeyCode::W, Action::CameraMoveNY);
-        bindings.bind(VirtualKeyCode::S, Action::CameraMovePY);
-        bindings.bind(VirtualKeyCode::A, Action::CameraMovePX);
-        bindings.bind(VirtualKeyCode::D, Action::CameraMoveNX);
-        bindings.bind(VirtualKeyCode::Left, Action::RotateShapePY);
-        bindings.bind(VirtualKeyCode::Right, Action::RotateShapeNY);
-        bindings.bind(VirtualKeyCode::Up, Action::RotateShapePX);
-        bindings.bind(VirtualKeyCode::Down, Action::RotateShapeNX);
+        let mut bindings = Bindings::new(2f32, 90f32, 90f32, 90f32, 45f32);
+        bindings.bind(Key::W, Action::CameraMoveNY);
+        bindings.bind(Key::S, Action::CameraMovePY);
+        bindings.bind(Key::A, Action::CameraMovePX);
+        bindings.bind(Key::D, Action::CameraMoveNX);
+        bindings.bind(Key::Left, Action::RotateShapePY);
+        bindings.bind(Key::Right, Action::RotateShapeNY);
+        bindings.bind(Key::Up, Action::RotateShapePX);
+        bindings.bind(Key::Down, Action::RotateShapeNX);
+        bindings.bind(Key::Q, Action::RotateShapePZ);
+        bindings.bind(Key::E, Action::RotateShapeNZ);
+        bindings.bind(Chord::new(Key::Q, Modifiers::SHIFT), Action::CameraRollP);
+        bindings.bind(Chord::new(Key::E, Modifiers::SHIFT), Action::CameraRollN);
+        bindings.bind(Key::P, Action::ToggleProjection);
+        bindings.bind(Chord::new(Key::R, Modifiers::CTRL), Action::ResetView);
+        bindings.bind(Key::V, Action::CycleShading);
+        bindings.bind(Key::T, Action::ToggleTurntable);
+        bindings.bind(Key::H, Action::ToggleHud);
+        bindings.bind(MouseButton::Right, Action::SelectFace);
+
+        // Drag with the left button to turn the shape as the arrow keys do. Wheel to zoom.
+        bindings.bind_axis(
+            AxisBinding::new(Axis::CursorX, Action::RotateShapeNY, 0.5)
+                .button(MouseButton::Left),
+        );
+        bindings.bind_axis(
+            AxisBinding::new(Axis::CursorY, Action::RotateShapeNX, 0.5)
+                .button(MouseButton::Left),
+        );
+        bindings.bind_axis(AxisBinding::new(Axis::WheelY, Action::CameraMoveNY, 0.25));
 
         bindings
     }
 }
 
-pub fn handle_keyboard<T: ActionState>(
-    event: &KeyboardInput, bindings: &Bindings, state: &mut T,
-) -> Option<(Camera, RotX, RotY)> {
-    let ci = bindings.camera_increment;
-    let xri = bindings.x_rotation_increment;
-    let yri = bindings.y_rotation_increment;
-    let vkc = event.virtual_keycode
-        .unwrap_or(VirtualKeyCode::Escape); // Escape is already caught beforehand.
-
-    bindings.bindings
-        .get(&vkc)
-        .map(|action| {
-            match event.state {
-                ElementState::Pressed => state.on(*action),
-                ElementState::Released => state.off(*action),
+/// Keys and mouse buttons currently held, the action each was resolved to, the modifiers
+/// last seen and where the cursor was. Needed to turn the right action off on release, to
+/// re-resolve held buttons when the modifiers change mid-hold, and to turn cursor
+/// positions into motion.
+#[derive(Debug, Clone, Default)]
+pub struct HeldInput {
+    buttons: HashMap<Button, Option<Action>>,
+    modifiers: Modifiers,
+    cursor: Option<Position>,
+}
+
+impl HeldInput {
+    /// Where the cursor was last seen in logical pixels from the top left of the window.
+    pub fn cursor(&self) -> Option<Position> {
+        self.cursor
+    }
+
+    fn is_held(&self, button: Button) -> bool {
+        self.buttons.contains_key(&button)
+    }
+
+    /// Re-resolve every held button when the modifiers differ from those last seen.
+    fn set_modifiers<T: ActionState>(
+        &mut self, modifiers: Modifiers, bindings: &Bindings, state: &mut T,
+    ) {
+        if modifiers == self.modifiers {
+            return;
+        }
+
+        self.modifiers = modifiers;
+        for (button, action) in self.buttons.iter_mut() {
+            let resolved = bindings.resolve(*button, modifiers);
+            if resolved != *action {
+                if let Some(old) = *action {
+                    state.off(old);
+                }
+                if let Some(new) = resolved {
+                    if bindings.kind(new) == ActionKind::Held {
+                        state.on(new);
+                    }
+                }
+                *action = resolved;
+            }
+        }
+    }
+}
+
+/// Press or release a key or mouse button.
+fn handle_button<T: ActionState>(
+    button: Button,
+    element: KeyState,
+    modifiers: Modifiers,
+    bindings: &Bindings,
+    held: &mut HeldInput,
+    state: &mut T,
+) -> Option<Action> {
+    held.set_modifiers(modifiers, bindings, state);
+
+    match element {
+        KeyState::Pressed if held.is_held(button) => None,
+        KeyState::Pressed => {
+            let action = bindings.resolve(button, modifiers);
+            held.buttons.insert(button, action);
+            let action = action?;
+            match bindings.kind(action) {
+                ActionKind::Held => state.on(action),
+                ActionKind::Trigger => state.trigger(action),
             }
-            (
-                state.camera_increment(ci),
-                state.x_rotation_increment(xri),
-                state.y_rotation_increment(yri),
-            )
-        })
+            Some(action)
+        },
+        KeyState::Released => {
+            if let Some(Some(action)) = held.buttons.remove(&button) {
+                state.off(action);
+            }
+            None
+        },
+    }
+}
+
+/// Update the action state from a keyboard event. Held actions are switched on and off and
+/// triggers are fired on press. Returns the bound action if the event freshly pressed it.
+/// Key repeats of an already held key and keys with no meaning, only a scan code, return
+/// `None`. Winit's `KeyboardInput` converts into a `KeyEvent`.
+///
+/// When the modifiers differ from the last event every held key and button is resolved
+/// again, so releasing Shift while holding Shift+Up switches from the chord's action to
+/// Up's. Triggers only fire on a press, never from a modifier change.
+pub fn handle_keyboard<T: ActionState>(
+    event: &KeyEvent, bindings: &Bindings, held: &mut HeldInput, state: &mut T,
+) -> Option<Action> {
+    let key = event.key?;
+
+    handle_button(Button::Key(key), event.state, event.modifiers, bindings, held, state)
+}
+
+/// How far the camera and scene move over a frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Increment {
+    pub camera: Camera,
+    pub camera_roll: Roll,
+    pub x: RotX,
+    pub y: RotY,
+    pub z: RotZ,
+}
+
+impl Default for Increment {
+    fn default() -> Self {
+        Increment {
+            camera: Camera::zero(),
+            camera_roll: Rad(0f32),
+            x: Rad(0f32),
+            y: Rad(0f32),
+            z: Rad(0f32),
+        }
+    }
+}
+
+impl Add for Increment {
+    type Output = Increment;
+
+    fn add(self, other: Increment) -> Increment {
+        Increment {
+            camera: self.camera + other.camera,
+            camera_roll: self.camera_roll + other.camera_roll,
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+/// Compute the transform of the held actions for a frame that lasted `elapsed`.
+pub fn frame_increment<T: ActionState>(
+    state: &T, bindings: &Bindings, elapsed: Duration,
+) -> Increment {
+    let secs = elapsed.as_secs_f32();
+    Increment {
+        camera: state.camera_increment(bindings.camera_speed * secs),
+        camera_roll: state.camera_roll_increment(bindings.camera_roll_speed * secs),
+        x: state.x_rotation_increment(bindings.x_rotation_speed * secs),
+        y: state.y_rotation_increment(bindings.y_rotation_speed * secs),
+        z: state.z_rotation_increment(bindings.z_rotation_speed * secs),
+    }
+}
+
+/// Compute the transform of the nudges taken for a frame. Camera nudges are in units and
+/// rotation and roll nudges in degrees. Nudges of other actions are left out.
+pub fn nudge_increment(nudged: &[(Action, f32)]) -> Increment {
+    let mut camera = Camera::zero();
+    let mut roll = 0.0;
+    let mut x = 0.0;
+    let mut y = 0.0;
+    let mut z = 0.0;
+
+    for &(action, amount) in nudged {
+        match action {
+            Action::CameraMovePX => camera.x += amount,
+            Action::CameraMoveNX => camera.x -= amount,
+            Action::CameraMovePY => camera.y += amount,
+            Action::CameraMoveNY => camera.y -= amount,
+            Action::CameraMovePZ => camera.z += amount,
+            Action::CameraMoveNZ => camera.z -= amount,
+            Action::CameraRollP => roll += amount,
+            Action::CameraRollN => roll -= amount,
+            Action::RotateShapePX => x += amount,
+            Action::RotateShapeNX => x -= amount,
+            Action::RotateShapePY => y += amount,
+            Action::RotateShapeNY => y -= amount,
+            Action::RotateShapePZ => z += amount,
+            Action::RotateShapeNZ => z -= amount,
+            _ => (),
+        }
+    }
+
+    Increment {
+        camera,
+        camera_roll: Deg(roll).into(),
+        x: Deg(x).into(),
+        y: Deg(y).into(),
+        z: Deg(z).into(),
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(actions: &[Action]) -> ActionSet {
+        let mut state = ActionSet::new();
+        for action in actions {
+            state.on(*action);
+        }
+        state
+    }
+
+    fn press(key: Key) -> KeyEvent {
+        KeyEvent::pressed(key)
+    }
+
+    fn release(key: Key) -> KeyEvent {
+        KeyEvent::released(key)
+    }
+
+    #[test]
+    fn action_set_switches_actions_on_and_off() {
+        let mut state = ActionSet::new();
+        assert!(state.is_idle());
+
+        state.on(Action::CameraMovePX);
+        state.on(Action::RotateShapeNY);
+        assert!(state.is_on(Action::CameraMovePX));
+        assert!(!state.is_on(Action::CameraMoveNX));
+        assert!(!state.is_idle());
+        assert_eq!(state.held(), vec![Action::CameraMovePX, Action::RotateShapeNY]);
+
+        state.off(Action::CameraMovePX);
+        state.off(Action::RotateShapeNY);
+        assert!(state.is_idle());
+        assert!(state.held().is_empty());
+    }
+
+    #[test]
+    fn action_set_turning_on_twice_needs_two_offs() {
+        let mut state = ActionSet::new();
+        state.on(Action::CameraMovePX);
+        state.on(Action::CameraMovePX);
+        state.off(Action::CameraMovePX);
+        assert!(state.is_on(Action::CameraMovePX));
+        state.off(Action::CameraMovePX);
+        assert!(state.is_idle());
+
+        // Switching off what isn't on doesn't count against the next switch on.
+        state.off(Action::CameraMovePX);
+        state.on(Action::CameraMovePX);
+        assert!(state.is_on(Action::CameraMovePX));
+    }
+
+    #[test]
+    fn action_stays_held_until_every_button_for_it_is_released() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Key::Up, Action::CameraMoveNY);
+        let mut held = HeldInput::default();
+        let mut state = ActionSet::new();
+
+        handle_keyboard(&press(Key::W), &bindings, &mut held, &mut state);
+        handle_keyboard(&press(Key::Up), &bindings, &mut held, &mut state);
+        handle_keyboard(&release(Key::W), &bindings, &mut held, &mut state);
+        assert!(state.is_on(Action::CameraMoveNY));
+
+        handle_keyboard(&release(Key::Up), &bindings, &mut held, &mut state);
+        assert!(state.is_idle());
+    }
+
+    #[test]
+    fn triggers_are_taken_once_in_order() {
+        let mut state = ActionSet::new();
+        state.trigger(Action::ResetView);
+        state.trigger(Action::ToggleProjection);
+        state.trigger(Action::ResetView);
+        assert!(state.is_idle());
+
+        assert_eq!(
+            state.take_triggered(),
+            vec![Action::ResetView, Action::ToggleProjection, Action::ResetView],
+        );
+        assert!(state.take_triggered().is_empty());
+    }
+
+    #[test]
+    fn nudges_add_up_per_action() {
+        let mut state = ActionSet::new();
+        state.nudge(Action::RotateShapeNY, 2.0);
+        state.nudge(Action::CameraMoveNY, 0.5);
+        state.nudge(Action::RotateShapeNY, -0.5);
+
+        assert_eq!(
+            state.take_nudged(),
+            vec![(Action::RotateShapeNY, 1.5), (Action::CameraMoveNY, 0.5)],
+        );
+        assert!(state.take_nudged().is_empty());
+    }
+
+    #[test]
+    fn single_directions_move_along_their_axis() {
+        let camera = |action| held(&[action]).camera_increment(2.0);
+        assert_eq!(camera(Action::CameraMovePX), Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(camera(Action::CameraMoveNY), Vector3::new(0.0, -2.0, 0.0));
+        assert_eq!(camera(Action::CameraMovePZ), Vector3::new(0.0, 0.0, 2.0));
+
+        let x: Rad<f32> = Deg(90.0).into();
+        assert_eq!(held(&[Action::RotateShapePX]).x_rotation_increment(90.0), x);
+        assert_eq!(held(&[Action::RotateShapeNY]).y_rotation_increment(90.0), -x);
+        assert_eq!(held(&[Action::RotateShapePZ]).z_rotation_increment(90.0), x);
+        assert_eq!(held(&[Action::CameraRollN]).camera_roll_increment(90.0), -x);
+    }
+
+    #[test]
+    fn opposing_directions_cancel_out() {
+        let state = held(&[
+            Action::CameraMovePX, Action::CameraMoveNX, Action::CameraMovePY,
+        ]);
+        assert_eq!(state.camera_increment(1.0), Vector3::new(0.0, 1.0, 0.0));
+
+        let state = held(&[
+            Action::RotateShapePX, Action::RotateShapeNX,
+            Action::RotateShapePY, Action::RotateShapeNY,
+            Action::RotateShapePZ, Action::RotateShapeNZ,
+            Action::CameraRollP, Action::CameraRollN,
+        ]);
+        assert_eq!(state.x_rotation_increment(90.0), Rad(0.0));
+        assert_eq!(state.y_rotation_increment(90.0), Rad(0.0));
+        assert_eq!(state.z_rotation_increment(90.0), Rad(0.0));
+        assert_eq!(state.camera_roll_increment(90.0), Rad(0.0));
+    }
+
+    #[test]
+    fn opposing_directions_resume_when_one_is_released() {
+        let mut state = held(&[Action::CameraMovePX, Action::CameraMoveNX]);
+        state.off(Action::CameraMoveNX);
+        assert_eq!(state.camera_increment(1.0), Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn frame_increment_scales_speeds_by_elapsed_time() {
+        let bindings = Bindings::new(2.0, 90.0, 60.0, 30.0, 10.0);
+        let state = held(&[
+            Action::CameraMoveNZ, Action::RotateShapePX, Action::RotateShapePY,
+            Action::RotateShapeNZ, Action::CameraRollP,
+        ]);
+
+        let increment = frame_increment(&state, &bindings, Duration::from_millis(500));
+        assert_eq!(increment.camera, Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(increment.x, Deg(45.0).into());
+        assert_eq!(increment.y, Deg(30.0).into());
+        assert_eq!(increment.z, Deg(-15.0).into());
+        assert_eq!(increment.camera_roll, Deg(5.0).into());
+    }
+
+    #[test]
+    fn nudge_increment_leaves_out_other_actions() {
+        let increment = nudge_increment(&[
+            (Action::CameraMoveNY, 0.5),
+            (Action::RotateShapeNX, 10.0),
+            (Action::ResetView, 1.0),
+            (Action::Custom(0), 1.0),
+        ]);
+        assert_eq!(increment.camera, Vector3::new(0.0, -0.5, 0.0));
+        assert_eq!(increment.x, Deg(-10.0).into());
+        assert_eq!(increment.y, Rad(0.0));
+    }
+
+    #[test]
+    fn chords_win_over_bare_keys() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Chord::new(Key::Up, Modifiers::SHIFT), Action::CameraMovePZ);
+
+        let up = |modifiers| bindings.resolve(Key::Up.into(), modifiers);
+        assert_eq!(up(Modifiers::NONE), Some(Action::RotateShapePX));
+        assert_eq!(up(Modifiers::SHIFT), Some(Action::CameraMovePZ));
+        // No chord for Ctrl+Up so the bare key is used.
+        assert_eq!(up(Modifiers::CTRL), Some(Action::RotateShapePX));
+        // A chord doesn't answer for its bare key.
+        assert_eq!(bindings.resolve(Key::R.into(), Modifiers::NONE), None);
+    }
+
+    #[test]
+    fn binding_replaces_and_unbinding_removes() {
+        let mut bindings = Bindings::default();
+        assert_eq!(bindings.bind(Key::W, Action::CameraMovePZ), Some(Action::CameraMoveNY));
+        let w = bindings.resolve(Key::W.into(), Modifiers::NONE);
+        assert_eq!(w, Some(Action::CameraMovePZ));
+        assert_eq!(bindings.unbind(Key::W), Some(Action::CameraMovePZ));
+        assert_eq!(bindings.resolve(Key::W.into(), Modifiers::NONE), None);
+        assert_eq!(bindings.unbind(Key::W), None);
+    }
+
+    #[test]
+    fn registered_actions_are_named_and_kept() {
+        let mut bindings = Bindings::default();
+        let spin = bindings.register("Spin", ActionKind::Held).unwrap();
+        let shot = bindings.register("Screenshot", ActionKind::Trigger).unwrap();
+        assert!(spin.is_custom());
+        assert_ne!(spin, shot);
+
+        assert_eq!(bindings.register("Spin", ActionKind::Trigger).unwrap(), spin);
+        assert_eq!(bindings.kind(spin), ActionKind::Trigger);
+        assert_eq!(bindings.action_named("Screenshot"), Some(shot));
+        assert_eq!(bindings.action_name(shot), Some("Screenshot"));
+        assert_eq!(bindings.action_named("ResetView"), Some(Action::ResetView));
+        assert_eq!(bindings.action_name(Action::ResetView), Some("ResetView"));
+        assert_eq!(bindings.action_named("Nothing"), None);
+    }
+
+    #[test]
+    fn built_in_names_are_reserved() {
+        let mut bindings = Bindings::default();
+        assert_eq!(
+            bindings.register("ResetView", ActionKind::Held),
+            Err(BindingsError::ReservedName("ResetView".to_owned())),
+        );
+    }
+
+    #[test]
+    fn every_built_in_action_has_a_kind() {
+        let bindings = Bindings::default();
+        for (action, name, kind) in BUILT_IN.iter() {
+            assert_eq!(bindings.kind(*action), *kind);
+            assert_eq!(bindings.action_named(name), Some(*action));
+        }
+    }
+
+    #[test]
+    fn binding_an_axis_replaces_the_same_axis() {
+        let mut bindings = Bindings::new(1.0, 1.0, 1.0, 1.0, 1.0);
+        bindings.bind_axis(AxisBinding::new(Axis::WheelY, Action::CameraMoveNY, 1.0));
+        bindings.bind_axis(AxisBinding::new(Axis::WheelY, Action::CameraMovePZ, 2.0));
+        bindings.bind_axis(
+            AxisBinding::new(Axis::WheelY, Action::CameraMoveNZ, 1.0)
+                .modifiers(Modifiers::CTRL),
+        );
+
+        let plain: Vec<&AxisBinding> = bindings
+            .axes()
+            .iter()
+            .filter(|b| b.modifiers.is_empty())
+            .collect();
+        assert_eq!(bindings.axes().len(), 2);
+        assert_eq!(plain.len(), 1);
+        assert_eq!((plain[0].action, plain[0].sensitivity), (Action::CameraMovePZ, 2.0));
+
+        bindings.unbind_axis(Axis::WheelY);
+        assert!(bindings.axes().is_empty());
+    }
+
+    #[test]
+    fn keys_hold_actions_until_released() {
+        let bindings = Bindings::default();
+        let mut keys = HeldInput::default();
+        let mut state = ActionSet::new();
+
+        assert_eq!(
+            handle_keyboard(&press(Key::W), &bindings, &mut keys, &mut state),
+            Some(Action::CameraMoveNY),
+        );
+        assert!(state.is_on(Action::CameraMoveNY));
+
+        let released = handle_keyboard(&release(Key::W), &bindings, &mut keys, &mut state);
+        assert_eq!(released, None);
+        assert!(state.is_idle());
+    }
+
+    #[test]
+    fn key_repeats_are_ignored() {
+        let bindings = Bindings::default();
+        let mut keys = HeldInput::default();
+        let mut state = ActionSet::new();
+
+        handle_keyboard(&press(Key::P), &bindings, &mut keys, &mut state);
+        assert_eq!(handle_keyboard(&press(Key::P), &bindings, &mut keys, &mut state), None);
+        assert_eq!(state.take_triggered(), vec![Action::ToggleProjection]);
+
+        handle_keyboard(&release(Key::P), &bindings, &mut keys, &mut state);
+        handle_keyboard(&press(Key::P), &bindings, &mut keys, &mut state);
+        assert_eq!(state.take_triggered(), vec![Action::ToggleProjection]);
+    }
+
+    #[test]
+    fn triggers_are_not_held() {
+        let bindings = Bindings::default();
+        let mut keys = HeldInput::default();
+        let mut state = ActionSet::new();
+
+        let ctrl_r = press(Key::R).modifiers(Modifiers::CTRL);
+        handle_keyboard(&ctrl_r, &bindings, &mut keys, &mut state);
+        assert!(state.is_idle());
+        assert_eq!(state.take_triggered(), vec![Action::ResetView]);
+    }
+
+    #[test]
+    fn unbound_and_unknown_keys_do_nothing() {
+        let bindings = Bindings::default();
+        let mut keys = HeldInput::default();
+        let mut state = ActionSet::new();
+
+        let pressed = handle_keyboard(&press(Key::F12), &bindings, &mut keys, &mut state);
+        assert_eq!(pressed, None);
+        let unknown = KeyEvent { key: None, scancode: 99, ..press(Key::W) };
+        assert_eq!(handle_keyboard(&unknown, &bindings, &mut keys, &mut state), None);
+        assert!(state.is_idle());
+        assert!(state.take_triggered().is_empty());
+    }
+
+    #[test]
+    fn releasing_a_key_turns_off_what_it_turned_on() {
+        let mut bindings = Bindings::default();
+        let mut keys = HeldInput::default();
+        let mut state = ActionSet::new();
+
+        handle_keyboard(&press(Key::W), &bindings, &mut keys, &mut state);
+        // Rebinding while held doesn't strand the action that's on.
+        bindings.bind(Key::W, Action::CameraMovePZ);
+        handle_keyboard(&release(Key::W), &bindings, &mut keys, &mut state);
+        assert!(state.is_idle());
+    }
+
+    #[test]
+    fn modifier_changes_re_resolve_held_keys() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Chord::new(Key::Up, Modifiers::SHIFT), Action::CameraMovePZ);
+        let mut keys = HeldInput::default();
+        let mut state = ActionSet::new();
+
+        handle_keyboard(&press(Key::Up), &bindings, &mut keys, &mut state);
+        assert_eq!(state.held(), vec![Action::RotateShapePX]);
+
+        let shift = press(Key::LShift).modifiers(Modifiers::SHIFT);
+        handle_keyboard(&shift, &bindings, &mut keys, &mut state);
+        assert_eq!(state.held(), vec![Action::CameraMovePZ]);
+
+        handle_keyboard(&release(Key::LShift), &bindings, &mut keys, &mut state);
+        assert_eq!(state.held(), vec![Action::RotateShapePX]);
+
+        handle_keyboard(&release(Key::Up), &bindings, &mut keys, &mut state);
+        assert!(state.is_idle());
+    }
+
+    #[test]
+    fn modifier_changes_do_not_fire_triggers() {
+        let bindings = Bindings::default();
+        let mut keys = HeldInput::default();
+        let mut state = ActionSet::new();
+
+        handle_keyboard(&press(Key::R), &bindings, &mut keys, &mut state);
+        let ctrl = press(Key::LControl).modifiers(Modifiers::CTRL);
+        handle_keyboard(&ctrl, &bindings, &mut keys, &mut state);
+        assert!(state.take_triggered().is_empty());
+    }
+
+    #[test]
+    fn right_click_selects_the_face_under_the_cursor() {
+        let bindings = Bindings::default();
+        let mut held = HeldInput::default();
+        let mut state = ActionSet::new();
+        assert_eq!(held.cursor(), None);
+
+        let position = Position::new(120.0, 80.0);
+        handle_cursor(position, Modifiers::NONE, &bindings, &mut held, &mut state);
+        let right = MouseButton::Right;
+        handle_mouse_button(
+            right, KeyState::Pressed, Modifiers::NONE, &bindings, &mut held, &mut state,
+        );
+        handle_mouse_button(
+            right, KeyState::Released, Modifiers::NONE, &bindings, &mut held, &mut state,
+        );
+
+        assert_eq!(held.cursor(), Some(position));
+        assert_eq!(state.take_triggered(), vec![Action::SelectFace]);
+        assert!(state.is_idle());
+    }
+
+    #[test]
+    fn key_events_ignore_scan_codes() {
+        let real = KeyEvent { scancode: 17, ..press(Key::W) };
+        assert_eq!(real, press(Key::W));
+        assert_ne!(real, release(Key::W));
+        assert_ne!(real, press(Key::W).modifiers(Modifiers::SHIFT));
+    }
+}
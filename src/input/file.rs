@@ -0,0 +1,456 @@
+//! Reading and writing `Bindings` as TOML.
+//!
+//! Keys are named as in `Key`, the same as winit's `VirtualKeyCode`. Mouse buttons are
+//! `Left`, `Right`, `Middle` or the number of any other button. Built in actions are named
+//! as in `Action` and custom actions by the name they were registered with. Speeds left out
+//! of the file fall back to the defaults.
+//!
+//! ```toml
+//! camera_speed = 2.0
+//! x_rotation_speed = 90.0
+//! y_rotation_speed = 90.0
+//! z_rotation_speed = 90.0
+//! camera_roll_speed = 45.0
+//!
+//! [[binding]]
+//! key = "Comma"
+//! action = "CameraMoveNY"
+//!
+//! [[binding]]
+//! key = "R"
+//! modifiers = { ctrl = true }
+//! action = "ResetView"
+//!
+//! [[binding]]
+//! button = "Middle"
+//! action = "ResetView"
+//!
+//! [[axis]]
+//! axis = "CursorX"
+//! button = "Left"
+//! action = "RotateShapeNY"
+//! sensitivity = 0.5
+//! ```
+use std::collections::HashMap;
+use std::path::Path;
+use std::{error, fmt, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+use crate::keyboard::Key;
+use super::{Action, Axis, AxisBinding, Bindings, Button, Chord, Modifiers, MouseButton};
+
+/// A bindings file can't be used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingsError {
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    UnknownKey(String),
+    UnknownButton(String),
+    UnknownAction(String),
+    /// A binding has both or neither of a key and a mouse button.
+    Target { action: String },
+    /// A custom action can't take the name of a built in action.
+    ReservedName(String),
+    /// The same key or button and modifiers are bound to two actions.
+    Conflict { key: String, modifiers: Modifiers, first: Action, second: Action },
+    /// The same axis, modifiers and drag button are bound to two actions.
+    AxisConflict { axis: Axis, modifiers: Modifiers, first: Action, second: Action },
+    Speed { name: &'static str, speed: f32 },
+    Sensitivity { axis: Axis, sensitivity: f32 },
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingsError::Parse(e) => write!(f, "Can't parse bindings. {}", e),
+            BindingsError::Serialize(e) => write!(f, "Can't write bindings. {}", e),
+            BindingsError::UnknownKey(key) => write!(f, "Unknown key name {:?}.", key),
+            BindingsError::UnknownButton(button) => write!(
+                f, "Unknown mouse button {:?}.", button,
+            ),
+            BindingsError::UnknownAction(action) => write!(
+                f, "Unknown action {:?}. Custom actions must be registered first.", action,
+            ),
+            BindingsError::Target { action } => write!(
+                f, "Binding for {:?} needs exactly one of a key or a button.", action,
+            ),
+            BindingsError::ReservedName(name) => write!(
+                f, "{:?} is the name of a built in action.", name,
+            ),
+            BindingsError::Conflict { key, modifiers, first, second } => write!(
+                f, "{}{} is bound to both {:?} and {:?}.", modifiers, key, first, second,
+            ),
+            BindingsError::AxisConflict { axis, modifiers, first, second } => write!(
+                f, "{}{:?} is bound to both {:?} and {:?}.", modifiers, axis, first, second,
+            ),
+            BindingsError::Speed { name, speed } => write!(
+                f, "The {} of {} must be positive.", name, speed,
+            ),
+            BindingsError::Sensitivity { axis, sensitivity } => write!(
+                f, "The sensitivity of {} for {:?} must be finite and not zero.",
+                sensitivity, axis,
+            ),
+        }
+    }
+}
+
+impl error::Error for BindingsError {}
+
+fn default_camera_speed() -> f32 {
+    Bindings::default().camera_speed
+}
+
+fn default_x_rotation_speed() -> f32 {
+    Bindings::default().x_rotation_speed
+}
+
+fn default_y_rotation_speed() -> f32 {
+    Bindings::default().y_rotation_speed
+}
+
+fn default_z_rotation_speed() -> f32 {
+    Bindings::default().z_rotation_speed
+}
+
+fn default_camera_roll_speed() -> f32 {
+    Bindings::default().camera_roll_speed
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BindingEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    button: Option<String>,
+    action: String,
+    /// Last as TOML needs tables after plain values.
+    #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+    modifiers: Modifiers,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AxisEntry {
+    axis: Axis,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    button: Option<String>,
+    action: String,
+    sensitivity: f32,
+    /// Last as TOML needs tables after plain values.
+    #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+    modifiers: Modifiers,
+}
+
+/// The file as written. Bindings are a list rather than a table so a key bound twice can
+/// be reported instead of silently keeping the last.
+#[derive(Debug, Serialize, Deserialize)]
+struct BindingsFile {
+    #[serde(default = "default_camera_speed")]
+    camera_speed: f32,
+    #[serde(default = "default_x_rotation_speed")]
+    x_rotation_speed: f32,
+    #[serde(default = "default_y_rotation_speed")]
+    y_rotation_speed: f32,
+    #[serde(default = "default_z_rotation_speed")]
+    z_rotation_speed: f32,
+    #[serde(default = "default_camera_roll_speed")]
+    camera_roll_speed: f32,
+    #[serde(default, rename = "binding")]
+    bindings: Vec<BindingEntry>,
+    #[serde(default, rename = "axis")]
+    axes: Vec<AxisEntry>,
+}
+
+fn parse_key(name: &str) -> Result<Key, BindingsError> {
+    toml::Value::String(name.to_owned())
+        .try_into()
+        .map_err(|_| BindingsError::UnknownKey(name.to_owned()))
+}
+
+pub(super) fn parse_button(name: &str) -> Result<MouseButton, BindingsError> {
+    match name {
+        "Left" => Ok(MouseButton::Left),
+        "Right" => Ok(MouseButton::Right),
+        "Middle" => Ok(MouseButton::Middle),
+        other => other
+            .parse()
+            .map(MouseButton::Other)
+            .map_err(|_| BindingsError::UnknownButton(name.to_owned())),
+    }
+}
+
+fn key_name(key: Key) -> String {
+    format!("{:?}", key)
+}
+
+pub(super) fn button_name(button: MouseButton) -> String {
+    match button {
+        MouseButton::Other(number) => number.to_string(),
+        named => format!("{:?}", named),
+    }
+}
+
+/// The key and button entries for whatever was pressed, one of them `None`.
+fn names(button: Button) -> (Option<String>, Option<String>) {
+    match button {
+        Button::Key(key) => (Some(key_name(key)), None),
+        Button::Mouse(button) => (None, Some(button_name(button))),
+    }
+}
+
+fn check_sensitivity(axis: Axis, sensitivity: f32) -> Result<f32, BindingsError> {
+    if sensitivity != 0.0 && sensitivity.is_finite() {
+        Ok(sensitivity)
+    } else {
+        Err(BindingsError::Sensitivity { axis, sensitivity })
+    }
+}
+
+fn check_speed(name: &'static str, speed: f32) -> Result<f32, BindingsError> {
+    if speed > 0.0 && speed.is_finite() {
+        Ok(speed)
+    } else {
+        Err(BindingsError::Speed { name, speed })
+    }
+}
+
+impl Bindings {
+    /// Parse bindings from TOML. Only the keys in the file are bound and only built in
+    /// actions are known. Use `apply_toml` to load bindings for registered actions.
+    pub fn from_toml(source: &str) -> Result<Self, BindingsError> {
+        let mut bindings = Bindings::default();
+        bindings.apply_toml(source)?;
+        Ok(bindings)
+    }
+
+    /// Replace the speeds and bindings with those parsed from TOML. Registered actions are
+    /// kept. Nothing changes if the TOML can't be used.
+    pub fn apply_toml(&mut self, source: &str) -> Result<(), BindingsError> {
+        let file: BindingsFile = toml::from_str(source).map_err(BindingsError::Parse)?;
+
+        let camera_speed = check_speed("camera_speed", file.camera_speed)?;
+        let x_rotation_speed = check_speed("x_rotation_speed", file.x_rotation_speed)?;
+        let y_rotation_speed = check_speed("y_rotation_speed", file.y_rotation_speed)?;
+        let z_rotation_speed = check_speed("z_rotation_speed", file.z_rotation_speed)?;
+        let camera_roll_speed = check_speed("camera_roll_speed", file.camera_roll_speed)?;
+
+        let mut bound: HashMap<Chord, Action> = HashMap::new();
+        for entry in file.bindings {
+            let action = self.action_named(&entry.action)
+                .ok_or_else(|| BindingsError::UnknownAction(entry.action.clone()))?;
+            let (name, button) = match (entry.key, entry.button) {
+                (Some(key), None) => {
+                    let button = Button::Key(parse_key(&key)?);
+                    (key, button)
+                },
+                (None, Some(name)) => {
+                    let button = Button::Mouse(parse_button(&name)?);
+                    (name, button)
+                },
+                _ => return Err(BindingsError::Target { action: entry.action }),
+            };
+            if let Some(first) = bound.insert(Chord::new(button, entry.modifiers), action) {
+                return Err(BindingsError::Conflict {
+                    key: name, modifiers: entry.modifiers, first, second: action,
+                });
+            }
+        }
+
+        let mut axes: Vec<AxisBinding> = Vec::new();
+        for entry in file.axes {
+            let action = self.action_named(&entry.action)
+                .ok_or_else(|| BindingsError::UnknownAction(entry.action.clone()))?;
+            let mut binding = AxisBinding::new(
+                entry.axis, action, check_sensitivity(entry.axis, entry.sensitivity)?,
+            ).modifiers(entry.modifiers);
+            if let Some(name) = entry.button {
+                binding = binding.button(parse_button(&name)?);
+            }
+            let key = (binding.axis, binding.modifiers, binding.button);
+            let clash = axes.iter().find(|b| (b.axis, b.modifiers, b.button) == key);
+            if let Some(first) = clash {
+                return Err(BindingsError::AxisConflict {
+                    axis: entry.axis,
+                    modifiers: entry.modifiers,
+                    first: first.action,
+                    second: action,
+                });
+            }
+            axes.push(binding);
+        }
+
+        self.bindings = bound;
+        self.axes = axes;
+        self.camera_speed = camera_speed;
+        self.x_rotation_speed = x_rotation_speed;
+        self.y_rotation_speed = y_rotation_speed;
+        self.z_rotation_speed = z_rotation_speed;
+        self.camera_roll_speed = camera_roll_speed;
+        Ok(())
+    }
+
+    /// Write out as TOML. Bindings are ordered by action then key or button name so saving
+    /// the same bindings twice gives the same file. Axes keep the order they were bound in.
+    pub fn to_toml(&self) -> Result<String, BindingsError> {
+        let name_of = |action: Action| {
+            self.action_name(action)
+                .map(str::to_owned)
+                .ok_or_else(|| BindingsError::UnknownAction(format!("{:?}", action)))
+        };
+
+        let mut bound: Vec<(&Chord, &Action)> = self.bindings.iter().collect();
+        bound.sort_by_key(|(chord, action)| {
+            (**action, names(chord.button), chord.modifiers)
+        });
+
+        let entries = bound
+            .into_iter()
+            .map(|(chord, action)| {
+                let (key, button) = names(chord.button);
+                Ok(BindingEntry {
+                    key,
+                    button,
+                    action: name_of(*action)?,
+                    modifiers: chord.modifiers,
+                })
+            })
+            .collect::<Result<Vec<BindingEntry>, BindingsError>>()?;
+
+        let axes = self.axes
+            .iter()
+            .map(|binding| Ok(AxisEntry {
+                axis: binding.axis,
+                button: binding.button.map(button_name),
+                action: name_of(binding.action)?,
+                sensitivity: binding.sensitivity,
+                modifiers: binding.modifiers,
+            }))
+            .collect::<Result<Vec<AxisEntry>, BindingsError>>()?;
+
+        let file = BindingsFile {
+            camera_speed: self.camera_speed,
+            x_rotation_speed: self.x_rotation_speed,
+            y_rotation_speed: self.y_rotation_speed,
+            z_rotation_speed: self.z_rotation_speed,
+            camera_roll_speed: self.camera_roll_speed,
+            bindings: entries,
+            axes,
+        };
+
+        toml::to_string(&file).map_err(BindingsError::Serialize)
+    }
+
+    /// Read bindings from a TOML file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let source = fs::read_to_string(path)?;
+        Ok(Bindings::from_toml(&source)?)
+    }
+
+    /// Replace the speeds and bindings with those read from a TOML file. Registered actions
+    /// are kept.
+    pub fn apply_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let source = fs::read_to_string(path)?;
+        Ok(self.apply_toml(&source)?)
+    }
+
+    /// Write the bindings to a TOML file, replacing whatever is there.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::ActionKind;
+
+    #[test]
+    fn default_bindings_round_trip() {
+        let bindings = Bindings::default();
+        let toml = bindings.to_toml().unwrap();
+        let read = Bindings::from_toml(&toml).unwrap();
+        assert_eq!(read.to_toml().unwrap(), toml);
+    }
+
+    #[test]
+    fn missing_speeds_fall_back_to_defaults() {
+        let bindings = Bindings::from_toml("camera_speed = 5.0").unwrap();
+        assert_eq!(bindings.camera_speed, 5.0);
+        assert_eq!(bindings.x_rotation_speed, Bindings::default().x_rotation_speed);
+        // Only what's in the file is bound.
+        assert_eq!(bindings.resolve(Key::W.into(), Modifiers::NONE), None);
+    }
+
+    #[test]
+    fn chords_and_buttons_are_read() {
+        let bindings = Bindings::from_toml(r#"
+            [[binding]]
+            key = "R"
+            action = "ResetView"
+            modifiers = { ctrl = true }
+
+            [[binding]]
+            button = "5"
+            action = "CycleShading"
+        "#).unwrap();
+
+        let r = |modifiers| bindings.resolve(Key::R.into(), modifiers);
+        assert_eq!(r(Modifiers::CTRL), Some(Action::ResetView));
+        assert_eq!(r(Modifiers::NONE), None);
+        assert_eq!(
+            bindings.resolve(Button::Mouse(MouseButton::Other(5)), Modifiers::NONE),
+            Some(Action::CycleShading),
+        );
+    }
+
+    #[test]
+    fn bad_files_are_reported() {
+        let error = |source| Bindings::from_toml(source).unwrap_err();
+
+        assert_eq!(
+            error("[[binding]]\nkey = \"Nope\"\naction = \"ResetView\""),
+            BindingsError::UnknownKey("Nope".to_owned()),
+        );
+        assert_eq!(
+            error("[[binding]]\nkey = \"R\"\naction = \"Nope\""),
+            BindingsError::UnknownAction("Nope".to_owned()),
+        );
+        assert_eq!(
+            error("[[binding]]\naction = \"ResetView\""),
+            BindingsError::Target { action: "ResetView".to_owned() },
+        );
+        assert_eq!(
+            error("camera_speed = -1.0"),
+            BindingsError::Speed { name: "camera_speed", speed: -1.0 },
+        );
+        assert_eq!(
+            error("[[binding]]\nkey = \"R\"\naction = \"ResetView\"\n\
+                   [[binding]]\nkey = \"R\"\naction = \"CycleShading\""),
+            BindingsError::Conflict {
+                key: "R".to_owned(),
+                modifiers: Modifiers::NONE,
+                first: Action::ResetView,
+                second: Action::CycleShading,
+            },
+        );
+    }
+
+    #[test]
+    fn failed_apply_changes_nothing() {
+        let mut bindings = Bindings::default();
+        let before = bindings.to_toml().unwrap();
+        let bad = "[[binding]]\nkey = \"Nope\"\naction = \"ResetView\"";
+        assert!(bindings.apply_toml(bad).is_err());
+        assert_eq!(bindings.to_toml().unwrap(), before);
+    }
+
+    #[test]
+    fn registered_actions_survive_applying_a_file() {
+        let mut bindings = Bindings::default();
+        let spin = bindings.register("Spin", ActionKind::Held).unwrap();
+        bindings.apply_toml("[[binding]]\nkey = \"S\"\naction = \"Spin\"").unwrap();
+        assert_eq!(bindings.resolve(Key::S.into(), Modifiers::NONE), Some(spin));
+    }
+}
@@ -0,0 +1,168 @@
+//! Mouse buttons, motion and the wheel.
+//!
+//! Buttons are bound like keys through `Bindings::bind`. Motion and the wheel are axes that
+//! nudge an action along by how far they moved, scaled by the binding's sensitivity.
+//! Motion can be limited to while a button is held for dragging.
+use serde::{Deserialize, Serialize};
+
+use crate::keyboard::KeyState;
+use super::{
+    handle_button, Action, ActionKind, ActionState, Bindings, Button, HeldInput, Modifiers,
+};
+
+/// Wheels that scroll by pixels are counted in lines of this many pixels, so the same
+/// binding works for both kinds.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// A mouse button. Named as in winit's `MouseButton`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u8),
+}
+
+/// Where the cursor is in logical pixels from the top left of the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Position {
+    pub fn new(x: f64, y: f64) -> Self {
+        Position { x, y }
+    }
+}
+
+/// How far the wheel scrolled. Wheels either step by lines or, like touchpads, by logical
+/// pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScrollDelta {
+    Lines(f32, f32),
+    Pixels(f64, f64),
+}
+
+impl ScrollDelta {
+    /// In lines, counting pixels in lines of `PIXELS_PER_LINE`.
+    pub fn lines(self) -> (f32, f32) {
+        match self {
+            ScrollDelta::Lines(x, y) => (x, y),
+            ScrollDelta::Pixels(x, y) => {
+                ((x / PIXELS_PER_LINE) as f32, (y / PIXELS_PER_LINE) as f32)
+            },
+        }
+    }
+}
+
+/// A direction the mouse moves in. Cursor axes are in logical pixels and wheel axes in
+/// lines. Positive is right and down for the cursor and right and up for the wheel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Axis {
+    CursorX,
+    CursorY,
+    WheelX,
+    WheelY,
+}
+
+/// Movement along an axis, while exactly these modifiers and the button if any are held,
+/// nudges an action. Built in camera actions move by `sensitivity` units and rotations by
+/// `sensitivity` degrees for each pixel or line. Trigger actions fire once per movement.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AxisBinding {
+    pub axis: Axis,
+    pub action: Action,
+    pub sensitivity: f32,
+    pub modifiers: Modifiers,
+    pub button: Option<MouseButton>,
+}
+
+impl AxisBinding {
+    pub fn new(axis: Axis, action: Action, sensitivity: f32) -> Self {
+        AxisBinding { axis, action, sensitivity, modifiers: Modifiers::NONE, button: None }
+    }
+
+    pub fn modifiers(self, modifiers: Modifiers) -> Self {
+        Self { modifiers, ..self }
+    }
+
+    /// Only while dragging with this button.
+    pub fn button(self, button: MouseButton) -> Self {
+        Self { button: Some(button), ..self }
+    }
+}
+
+/// Nudge the actions bound to an axis by `amount`. As with keys, bindings with exactly the
+/// held modifiers win, otherwise those without modifiers are used.
+fn handle_axis<T: ActionState>(
+    axis: Axis, amount: f32, bindings: &Bindings, held: &HeldInput, state: &mut T,
+) {
+    if amount == 0.0 {
+        return;
+    }
+
+    let active = |modifiers: Modifiers| {
+        bindings.axes().iter().filter(move |b| {
+            b.axis == axis
+                && b.modifiers == modifiers
+                && b.button.into_iter().all(|button| held.is_held(Button::Mouse(button)))
+        })
+    };
+    let modifiers = if active(held.modifiers).next().is_some() {
+        held.modifiers
+    } else {
+        Modifiers::NONE
+    };
+
+    for binding in active(modifiers) {
+        match bindings.kind(binding.action) {
+            ActionKind::Held => state.nudge(binding.action, amount * binding.sensitivity),
+            ActionKind::Trigger => state.trigger(binding.action),
+        }
+    }
+}
+
+/// Update the action state from a mouse button event, as `handle_keyboard` does for keys.
+pub fn handle_mouse_button<T: ActionState>(
+    button: MouseButton,
+    element: KeyState,
+    modifiers: Modifiers,
+    bindings: &Bindings,
+    held: &mut HeldInput,
+    state: &mut T,
+) -> Option<Action> {
+    handle_button(Button::Mouse(button), element, modifiers, bindings, held, state)
+}
+
+/// Nudge the actions bound to the cursor axes by how far it moved since the last event.
+/// The first position seen only sets where the cursor is.
+pub fn handle_cursor<T: ActionState>(
+    position: Position,
+    modifiers: Modifiers,
+    bindings: &Bindings,
+    held: &mut HeldInput,
+    state: &mut T,
+) {
+    held.set_modifiers(modifiers, bindings, state);
+
+    if let Some(last) = held.cursor.replace(position) {
+        handle_axis(Axis::CursorX, (position.x - last.x) as f32, bindings, held, state);
+        handle_axis(Axis::CursorY, (position.y - last.y) as f32, bindings, held, state);
+    }
+}
+
+/// Nudge the actions bound to the wheel axes by how far it scrolled.
+pub fn handle_wheel<T: ActionState>(
+    delta: ScrollDelta,
+    modifiers: Modifiers,
+    bindings: &Bindings,
+    held: &mut HeldInput,
+    state: &mut T,
+) {
+    held.set_modifiers(modifiers, bindings, state);
+
+    let (x, y) = delta.lines();
+    handle_axis(Axis::WheelX, x, bindings, held, state);
+    handle_axis(Axis::WheelY, y, bindings, held, state);
+}
@@ -0,0 +1,222 @@
+//! Recording input frame by frame and playing it back.
+//!
+//! A recording holds the input events that arrived before each frame along with how long
+//! the frame lasted. Playing the frames back through the same bindings and action state
+//! gives the same held actions, triggers and nudges every frame, so the camera and scene
+//! rotation follow the same path no matter how fast frames are actually drawn.
+//!
+//! Recordings are written as TOML with each frame's duration in nanoseconds so nothing is
+//! lost to rounding.
+//!
+//! ```toml
+//! [[frame]]
+//! elapsed = 16666666
+//!
+//! [[frame.event]]
+//! kind = "Key"
+//! key = "W"
+//! pressed = true
+//! ```
+use std::path::Path;
+use std::time::Duration;
+use std::{error, fmt, fs, mem};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Error;
+use crate::keyboard::{Key, KeyState};
+use super::{
+    handle_button, file, mouse, ActionState, Bindings, Button, HeldInput, Modifiers,
+};
+use super::mouse::{MouseButton, Position, ScrollDelta};
+
+/// A recording can't be read or written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingError {
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordingError::Parse(e) => write!(f, "Can't parse recording. {}", e),
+            RecordingError::Serialize(e) => write!(f, "Can't write recording. {}", e),
+        }
+    }
+}
+
+impl error::Error for RecordingError {}
+
+fn element(pressed: bool) -> KeyState {
+    if pressed {
+        KeyState::Pressed
+    } else {
+        KeyState::Released
+    }
+}
+
+/// Mouse buttons are named as in bindings files since TOML can't hold `Other(u8)` as is.
+fn serialize_button<S: Serializer>(button: &MouseButton, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&file::button_name(*button))
+}
+
+fn deserialize_button<'de, D: Deserializer<'de>>(d: D) -> Result<MouseButton, D::Error> {
+    let name = String::deserialize(d)?;
+    file::parse_button(&name).map_err(de::Error::custom)
+}
+
+/// Input that can affect the action state. Wheel movement is kept in lines and the cursor
+/// position in logical pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum InputEvent {
+    Key {
+        key: Key,
+        pressed: bool,
+        #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+        modifiers: Modifiers,
+    },
+    MouseButton {
+        #[serde(
+            serialize_with = "serialize_button",
+            deserialize_with = "deserialize_button",
+        )]
+        button: MouseButton,
+        pressed: bool,
+        #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+        modifiers: Modifiers,
+    },
+    Cursor {
+        x: f64,
+        y: f64,
+        #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+        modifiers: Modifiers,
+    },
+    Wheel {
+        x: f32,
+        y: f32,
+        #[serde(default, skip_serializing_if = "Modifiers::is_empty")]
+        modifiers: Modifiers,
+    },
+}
+
+impl InputEvent {
+    /// Update the action state as the `handle_` functions do for the window event.
+    pub fn apply<T: ActionState>(
+        &self, bindings: &Bindings, held: &mut HeldInput, state: &mut T,
+    ) {
+        match *self {
+            InputEvent::Key { key, pressed, modifiers } => {
+                handle_button(
+                    Button::Key(key), element(pressed), modifiers, bindings, held, state,
+                );
+            },
+            InputEvent::MouseButton { button, pressed, modifiers } => {
+                mouse::handle_mouse_button(
+                    button, element(pressed), modifiers, bindings, held, state,
+                );
+            },
+            InputEvent::Cursor { x, y, modifiers } => mouse::handle_cursor(
+                Position::new(x, y), modifiers, bindings, held, state,
+            ),
+            InputEvent::Wheel { x, y, modifiers } => mouse::handle_wheel(
+                ScrollDelta::Lines(x, y), modifiers, bindings, held, state,
+            ),
+        }
+    }
+}
+
+/// The input that arrived before a frame and how long the frame lasted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub elapsed: Duration,
+    pub events: Vec<InputEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FrameEntry {
+    /// Nanoseconds.
+    elapsed: u64,
+    #[serde(default, rename = "event", skip_serializing_if = "Vec::is_empty")]
+    events: Vec<InputEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordingFile {
+    #[serde(default, rename = "frame")]
+    frames: Vec<FrameEntry>,
+}
+
+/// Input recorded frame by frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    frames: Vec<Frame>,
+    /// Events for the frame not yet ended.
+    pending: Vec<InputEvent>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Recording::default()
+    }
+
+    /// Add an event to the current frame.
+    pub fn record(&mut self, event: InputEvent) {
+        self.pending.push(event);
+    }
+
+    /// End the current frame. Events recorded since the last frame ended belong to it.
+    pub fn end_frame(&mut self, elapsed: Duration) {
+        let events = mem::take(&mut self.pending);
+        self.frames.push(Frame { elapsed, events });
+    }
+
+    /// Every ended frame in order.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Ended frames in order. Events of a frame that wasn't ended are dropped.
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.frames
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, RecordingError> {
+        let file: RecordingFile = toml::from_str(source).map_err(RecordingError::Parse)?;
+        let frames = file.frames
+            .into_iter()
+            .map(|entry| Frame {
+                elapsed: Duration::from_nanos(entry.elapsed),
+                events: entry.events,
+            })
+            .collect();
+
+        Ok(Recording { frames, pending: Vec::new() })
+    }
+
+    /// Write out the ended frames as TOML.
+    pub fn to_toml(&self) -> Result<String, RecordingError> {
+        let frames = self.frames
+            .iter()
+            .map(|frame| FrameEntry {
+                elapsed: frame.elapsed.as_nanos() as u64,
+                events: frame.events.clone(),
+            })
+            .collect();
+
+        toml::to_string(&RecordingFile { frames }).map_err(RecordingError::Serialize)
+    }
+
+    /// Read a recording from a TOML file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let source = fs::read_to_string(path)?;
+        Ok(Recording::from_toml(&source)?)
+    }
+
+    /// Write the recording to a TOML file, replacing whatever is there.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
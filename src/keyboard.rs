@@ -1,58 +1,141 @@
-//! Keyboard handling for winit.
+//! Key events apart from any windowing library.
 //!
-//! TODO: Get the correct scan codes for the statics. Will still rely on the newtype.
-use std::cmpd::
+//! Input handling works on `KeyEvent`s so it can be driven by synthetic events in tests or
+//! by windowing libraries other than winit. Conversions from winit's types are kept with
+//! the rest of the window event handling.
+use std::fmt;
+use std::ops::Not;
 
-use wgpu::winit::{KeyboardInput, VirtualKeyCode, ElementState, ModifiersState};
+use serde::{Deserialize, Serialize};
 
-/// Newtype for `KeyboardInput`. The `Eq` impl skips comparison on the scan code.
-#[derive(Debug, Copy, Clone)]
-pub struct KeyEvent(pub KeyboardInput);
+/// Pass every key, named as in winit's `VirtualKeyCode`, to the macro `$then`. Lets the
+/// conversions to and from winit list the same keys as `Key`.
+macro_rules! with_keys {
+    ($then:ident) => {
+        $then! {
+            Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0, A, B, C, D, E, F, G,
+            H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Escape, F1, F2, F3, F4,
+            F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21,
+            F22, F23, F24, Snapshot, Scroll, Pause, Insert, Home, Delete, End, PageDown,
+            PageUp, Left, Up, Right, Down, Back, Return, Space, Compose, Caret, Numlock,
+            Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8,
+            Numpad9, AbntC1, AbntC2, Add, Apostrophe, Apps, At, Ax, Backslash, Calculator,
+            Capital, Colon, Comma, Convert, Decimal, Divide, Equals, Grave, Kana, Kanji,
+            LAlt, LBracket, LControl, LShift, LWin, Mail, MediaSelect, MediaStop, Minus,
+            Multiply, Mute, MyComputer, NavigateForward, NavigateBackward, NextTrack,
+            NoConvert, NumpadComma, NumpadEnter, NumpadEquals, OEM102, Period, PlayPause,
+            Power, PrevTrack, RAlt, RBracket, RControl, RShift, RWin, Semicolon, Slash,
+            Sleep, Stop, Subtract, Sysrq, Tab, Underline, Unlabeled, VolumeDown, VolumeUp,
+            Wake, WebBack, WebFavorites, WebForward, WebHome, WebRefresh, WebSearch,
+            WebStop, Yen, Copy, Paste, Cut,
+        }
+    }
+}
 
+macro_rules! declare_keys {
+    ($($key:ident),* $(,)?) => {
+        /// A key by what it means rather than where it is. Named as in winit's
+        /// `VirtualKeyCode`.
+        #[derive(
+            Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize,
+            Deserialize,
+        )]
+        pub enum Key {
+            $($key),*
+        }
+    }
+}
 
+with_keys!(declare_keys);
 
-pub static no_mod: ModifiersState = ModifiersState {
-    shift: false, ctrl: false, alt: false, logo: false,
-};
+/// Whether a key or button went down or came up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum KeyState {
+    Pressed,
+    Released,
+}
 
-macro_rules! make_key_event {
-    ($name:ident, $vkc:ty) => {
-        pub static $name: KeyEvent = KeyEvent(KeyboardInput {
-            state: ElementState::Pressed,
-            virtual_keycode: Some($vkc),
-            modifiers: no_mod,
-            scancode: 0,
-        });
+/// Modifier keys held along with a key.
+#[derive(
+    Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize,
+    Deserialize,
+)]
+#[serde(default)]
+pub struct Modifiers {
+    #[serde(skip_serializing_if = "Not::not")]
+    pub shift: bool,
+    #[serde(skip_serializing_if = "Not::not")]
+    pub ctrl: bool,
+    #[serde(skip_serializing_if = "Not::not")]
+    pub alt: bool,
+    #[serde(skip_serializing_if = "Not::not")]
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false, ctrl: false, alt: false, logo: false,
+    };
+    pub const SHIFT: Modifiers = Modifiers { shift: true, ..Modifiers::NONE };
+    pub const CTRL: Modifiers = Modifiers { ctrl: true, ..Modifiers::NONE };
+    pub const ALT: Modifiers = Modifiers { alt: true, ..Modifiers::NONE };
+    pub const LOGO: Modifiers = Modifiers { logo: true, ..Modifiers::NONE };
+
+    pub fn is_empty(&self) -> bool {
+        *self == Modifiers::NONE
+    }
+}
 
-        
+/// Written as a prefix to the key, such as `Ctrl+`.
+impl fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            (self.shift, "Shift"), (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"), (self.logo, "Logo"),
+        ];
+        for (_, name) in names.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        Ok(())
     }
 }
 
-pub static d_left: KeyEvent = KeyEvent(KeyboardInput {
-    state: ElementState::Pressed,
-    virtual_keycode: Some(VirtualKeyCode::Left),
-    modifiers: no_mod,
-    scancode: 0,
-});
+/// A key pressed or released. The `Eq` impl skips comparison on the scan code as it differs
+/// between keyboards and platforms, and synthetic events don't have one.
+#[derive(Debug, Copy, Clone)]
+pub struct KeyEvent {
+    pub scancode: u32,
+    /// `None` when the key has no meaning, only a scan code.
+    pub key: Option<Key>,
+    pub state: KeyState,
+    pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+    pub fn new(key: Key, state: KeyState) -> Self {
+        KeyEvent { scancode: 0, key: Some(key), state, modifiers: Modifiers::NONE }
+    }
 
-pub static d_right: KeyEvent = KeyEvent(KeyboardInput {
-    state: ElementState::Pressed,
-    virtual_keycode: Some(VirtualKeyCode::Right),
-    modifiers: no_mod,
-    scancode: 0,
-});
+    pub fn pressed(key: Key) -> Self {
+        KeyEvent::new(key, KeyState::Pressed)
+    }
 
-pub static d_up: KeyEvent = KeyEvent(KeyboardInput {
-    state: ElementState::Pressed,
-    virtual_keycode: Some(VirtualKeyCode::Up),
-    modifiers: no_mod,
-    scancode: 0,
-});
+    pub fn released(key: Key) -> Self {
+        KeyEvent::new(key, KeyState::Released)
+    }
 
-pub static d_down: KeyEvent = KeyEvent(KeyboardInput {
-    state: ElementState::Pressed,
-    virtual_keycode: Some(VirtualKeyCode::Down),
-    modifiers: no_mod,
-    scancode: 0,
-});
+    /// With these modifiers held.
+    pub fn modifiers(self, modifiers: Modifiers) -> Self {
+        KeyEvent { modifiers, ..self }
+    }
+}
+
+impl PartialEq for KeyEvent {
+    fn eq(&self, other: &KeyEvent) -> bool {
+        self.key == other.key
+            && self.state == other.state
+            && self.modifiers == other.modifiers
+    }
+}
 
+impl Eq for KeyEvent {}
@@ -3,8 +3,18 @@
 //! Render various Goldberg polyhedrons.
 
 /*pub mod shape;*/
+#[macro_use]
+pub mod keyboard;
 pub mod input;
+mod window;
 pub mod scene;
 pub mod light;
+pub mod bounds;
+pub mod pick;
 pub mod shader;
+pub mod pipeline;
+pub mod hud;
 pub mod presentation;
+pub mod error;
+
+pub use error::Error;
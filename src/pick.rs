@@ -0,0 +1,145 @@
+//! Finding the face of the geometry under the cursor.
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+
+/// A ray in the geometry's own space, starting at the near plane.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// The ray through a point on the screen in normalised device coordinates, x right and
+    /// y up from -1 to 1. `transform` is everything applied to the geometry to draw it, so
+    /// the projection and view times the rotation. `None` when it can't be inverted.
+    pub fn unproject(transform: Matrix4<f32>, x: f32, y: f32) -> Option<Self> {
+        let inverse = transform.invert()?;
+        let point = |z: f32| Point3::from_homogeneous(inverse * Vector4::new(x, y, z, 1.0));
+        let (near, far) = (point(-1.0), point(1.0));
+
+        let direction = far - near;
+        if !direction.magnitude2().is_normal() {
+            return None;
+        }
+
+        Some(Ray { origin: near, direction: direction.normalize() })
+    }
+
+    /// Distance along the ray to where it crosses the triangle, from either side.
+    pub fn hits(&self, a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> Option<f32> {
+        let (ab, ac) = (b - a, c - a);
+        let p = self.direction.cross(ac);
+        let det = ab.dot(p);
+        if det.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        // Barycentric coordinates of the crossing then the distance to it.
+        let to_origin = self.origin - a;
+        let u = to_origin.dot(p) / det;
+        let q = to_origin.cross(ab);
+        let v = self.direction.dot(q) / det;
+        if u < 0.0 || v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = ac.dot(q) / det;
+        if distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+/// Number of the nearest triangle the ray crosses in an indexed triangle list. Triangles
+/// are numbered in index order from 0.
+pub fn nearest_face(ray: &Ray, positions: &[Point3<f32>], index: &[u16]) -> Option<u32> {
+    let corner = |i: u16| positions.get(i as usize).copied();
+
+    index
+        .chunks_exact(3)
+        .enumerate()
+        .filter_map(|(face, triangle)| {
+            let a = corner(triangle[0])?;
+            let (b, c) = (corner(triangle[1])?, corner(triangle[2])?);
+            ray.hits(a, b, c).map(|distance| (face as u32, distance))
+        })
+        .fold(None, |nearest: Option<(u32, f32)>, (face, distance)| match nearest {
+            Some((_, closest)) if closest <= distance => nearest,
+            _ => Some((face, distance)),
+        })
+        .map(|(face, _)| face)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::Deg;
+
+    /// Two squares facing +z, one at z = 0 and one at z = 1, each as two triangles.
+    fn stacked() -> (Vec<Point3<f32>>, Vec<u16>) {
+        let square = |z: f32| vec![
+            Point3::new(-1.0, -1.0, z),
+            Point3::new(1.0, -1.0, z),
+            Point3::new(1.0, 1.0, z),
+            Point3::new(-1.0, 1.0, z),
+        ];
+        let positions = [square(0.0), square(1.0)].concat();
+        let index = vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7];
+        (positions, index)
+    }
+
+    fn down_from(x: f32, y: f32) -> Ray {
+        Ray { origin: Point3::new(x, y, 5.0), direction: -Vector3::unit_z() }
+    }
+
+    #[test]
+    fn nearest_face_is_picked() {
+        let (positions, index) = stacked();
+        assert_eq!(nearest_face(&down_from(0.5, -0.5), &positions, &index), Some(2));
+        assert_eq!(nearest_face(&down_from(-0.5, 0.5), &positions, &index), Some(3));
+    }
+
+    #[test]
+    fn faces_behind_the_ray_or_beside_it_are_missed() {
+        let (positions, index) = stacked();
+        assert_eq!(nearest_face(&down_from(2.0, 0.0), &positions, &index), None);
+
+        let up = Ray { origin: Point3::new(0.5, -0.5, 5.0), direction: Vector3::unit_z() };
+        assert_eq!(nearest_face(&up, &positions, &index), None);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_skipped() {
+        let (positions, _) = stacked();
+        let index = [0, 1, 9, 4, 5, 6];
+        assert_eq!(nearest_face(&down_from(0.5, -0.5), &positions, &index), Some(1));
+    }
+
+    #[test]
+    fn unprojected_ray_goes_through_the_screen_point() {
+        let transform = cgmath::perspective(Deg(90.0), 1.0, 1.0, 10.0)
+            * Matrix4::look_at(
+                Point3::new(0.0, 0.0, 5.0), Point3::new(0.0, 0.0, 0.0), Vector3::unit_y(),
+            );
+
+        let centre = Ray::unproject(transform, 0.0, 0.0).unwrap();
+        assert!((centre.origin - Point3::new(0.0, 0.0, 4.0)).magnitude() < 1e-4);
+        assert!((centre.direction + Vector3::unit_z()).magnitude() < 1e-4);
+
+        // The right edge of the screen is 45 degrees off the view axis.
+        let right = Ray::unproject(transform, 1.0, 0.0).unwrap();
+        assert!((right.direction.x - right.direction.z.abs()).abs() < 1e-4);
+
+        let (positions, index) = stacked();
+        let picked = Ray::unproject(transform, 0.1, -0.1).unwrap();
+        assert_eq!(nearest_face(&picked, &positions, &index), Some(2));
+    }
+
+    #[test]
+    fn singular_transform_has_no_ray() {
+        assert_eq!(Ray::unproject(Matrix4::from_scale(0.0), 0.0, 0.0), None);
+    }
+}
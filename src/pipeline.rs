@@ -0,0 +1,237 @@
+//! What a pair of shaders expects to be fed. The scene builds its vertex buffer layout,
+//! bind group and uniform buffers from a `Layout` so shaders other than the flat shader
+//! can be plugged in.
+use crate::light::LightRaw;
+use crate::shader::{CompiledShaders, MAX_LIGHTS};
+use crate::shader::reflect::{Stage, UniformBinding};
+
+/// Per vertex data a shader can take. Every `Vertex` carries all of them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Attribute {
+    Position,
+    Normal,
+    Colour,
+}
+
+impl Attribute {
+    /// Byte offset into a `Vertex`. Each attribute is a packed `Float3`.
+    pub(crate) fn offset(self) -> u64 {
+        match self {
+            Attribute::Position => 0,
+            Attribute::Normal => 4 * 3,
+            Attribute::Colour => 4 * 6,
+        }
+    }
+
+    /// Number of floats the attribute feeds the shader.
+    pub(crate) fn components(self) -> u32 {
+        3
+    }
+}
+
+/// Uniform buffers the scene can fill in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Uniform {
+    /// Camera projection and view as a `mat4`.
+    Projection,
+    /// Model rotation as a `mat4`.
+    Rotation,
+    /// `MAX_LIGHTS` lights as laid out in `light.glsl`.
+    Lights,
+    /// Number of lights in use as an `int`.
+    LightCount,
+    /// Near and far planes as `float`s then an `int` that's 1 when orthographic.
+    Clip,
+    /// Bytes owned by the caller. Zeroed until set with `Scene::set_uniform`.
+    Custom { size: u64 },
+}
+
+impl Uniform {
+    /// Size of the buffer in bytes.
+    pub fn size(self) -> u64 {
+        match self {
+            Uniform::Projection | Uniform::Rotation => 64,
+            Uniform::Lights => (MAX_LIGHTS * LightRaw::sizeof()) as u64,
+            Uniform::LightCount => 4,
+            Uniform::Clip => 12,
+            Uniform::Custom { size } => size,
+        }
+    }
+}
+
+/// A uniform buffer bound in set 0 and the stages that can see it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub binding: u32,
+    pub uniform: Uniform,
+    pub stages: &'static [Stage],
+}
+
+impl Binding {
+    pub(crate) fn visibility(&self) -> wgpu::ShaderStage {
+        self.stages.iter().fold(wgpu::ShaderStage::empty(), |visibility, stage| {
+            visibility | match stage {
+                Stage::Vertex => wgpu::ShaderStage::VERTEX,
+                Stage::Fragment => wgpu::ShaderStage::FRAGMENT,
+            }
+        })
+    }
+}
+
+/// Vertex attributes by shader location and the uniform buffers to bind. Built by chaining
+/// from `Layout::new` or starting from `Layout::flat`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Layout {
+    attributes: Vec<(u32, Attribute)>,
+    bindings: Vec<Binding>,
+    unindexed: bool,
+}
+
+impl Layout {
+    /// No attributes or bindings.
+    pub fn new() -> Self {
+        Layout::default()
+    }
+
+    /// What the bundled flat shader takes. Position, normal and colour at locations 0 to 2.
+    /// Projection and rotation for the vertex stage at bindings 0 and 1, then the lights
+    /// and their count for the fragment stage at bindings 2 and 3.
+    pub fn flat() -> Self {
+        Layout::new()
+            .attribute(0, Attribute::Position)
+            .attribute(1, Attribute::Normal)
+            .attribute(2, Attribute::Colour)
+            .uniform(0, Uniform::Projection, &[Stage::Vertex])
+            .uniform(1, Uniform::Rotation, &[Stage::Vertex])
+            .uniform(2, Uniform::Lights, &[Stage::Fragment])
+            .uniform(3, Uniform::LightCount, &[Stage::Fragment])
+    }
+
+    /// Feed an attribute to the vertex shader input at `location`.
+    pub fn attribute(mut self, location: u32, attribute: Attribute) -> Self {
+        self.attributes.retain(|(l, _)| *l != location);
+        self.attributes.push((location, attribute));
+        self
+    }
+
+    /// Bind a uniform buffer at `binding` in set 0, visible to `stages`.
+    pub fn uniform(
+        mut self, binding: u32, uniform: Uniform, stages: &'static [Stage],
+    ) -> Self {
+        self.bindings.retain(|b| b.binding != binding);
+        self.bindings.push(Binding { binding, uniform, stages });
+        self
+    }
+
+    /// Draw without the index buffer, repeating shared vertices for every triangle. The
+    /// vertex shader can then tell the corners of a triangle apart with `gl_VertexIndex`.
+    pub fn draw_unindexed(self) -> Self {
+        Layout { unindexed: true, ..self }
+    }
+
+    pub fn attributes(&self) -> &[(u32, Attribute)] {
+        &self.attributes
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    pub fn unindexed(&self) -> bool {
+        self.unindexed
+    }
+
+    /// Shader locations that are fed an attribute along with how many floats they get.
+    pub(crate) fn locations(&self) -> Vec<(u32, u32)> {
+        self.attributes
+            .iter()
+            .map(|(location, attribute)| (*location, attribute.components()))
+            .collect()
+    }
+
+    /// Size of the custom uniform at `binding` if there's one there.
+    pub(crate) fn custom_size(&self, binding: u32) -> Option<u64> {
+        self.bindings
+            .iter()
+            .find(|b| b.binding == binding)
+            .and_then(|b| match b.uniform {
+                Uniform::Custom { size } => Some(size),
+                _ => None,
+            })
+    }
+
+    /// The bindings as checked against shader reflection.
+    pub(crate) fn uniform_bindings(&self) -> Vec<UniformBinding> {
+        self.bindings
+            .iter()
+            .map(|b| UniformBinding {
+                binding: b.binding,
+                size: b.uniform.size(),
+                stages: b.stages,
+            })
+            .collect()
+    }
+
+    pub(crate) fn vertex_attributes(&self) -> Vec<wgpu::VertexAttributeDescriptor> {
+        self.attributes
+            .iter()
+            .map(|(location, attribute)| wgpu::VertexAttributeDescriptor {
+                format: wgpu::VertexFormat::Float3,
+                offset: attribute.offset(),
+                shader_location: *location,
+            })
+            .collect()
+    }
+}
+
+/// Shaders along with the layout they expect. The defaults match the flat shader so any
+/// shaders written against its interface only need an empty impl.
+pub trait Pipeline: CompiledShaders {
+    fn layout(&self) -> Layout {
+        Layout::flat()
+    }
+
+    /// Faces to skip drawing.
+    fn cull_mode(&self) -> wgpu::CullMode {
+        wgpu::CullMode::Back
+    }
+}
+
+/// Any compiled shaders paired with a layout of the caller's choosing.
+#[derive(Debug, Clone)]
+pub struct Material<T: CompiledShaders> {
+    shaders: T,
+    layout: Layout,
+    cull_mode: wgpu::CullMode,
+}
+
+impl<T: CompiledShaders> Material<T> {
+    /// Back faces are culled by default.
+    pub fn new(shaders: T, layout: Layout) -> Self {
+        Material { shaders, layout, cull_mode: wgpu::CullMode::Back }
+    }
+
+    pub fn cull_mode(self, cull_mode: wgpu::CullMode) -> Self {
+        Material { cull_mode, ..self }
+    }
+}
+
+impl<T: CompiledShaders> CompiledShaders for Material<T> {
+    fn fragment(&self) -> &[u8] {
+        self.shaders.fragment()
+    }
+
+    fn vertex(&self) -> &[u8] {
+        self.shaders.vertex()
+    }
+}
+
+impl<T: CompiledShaders> Pipeline for Material<T> {
+    fn layout(&self) -> Layout {
+        self.layout.clone()
+    }
+
+    fn cull_mode(&self) -> wgpu::CullMode {
+        self.cull_mode
+    }
+}
@@ -1,19 +1,35 @@
 //! Present the whole thing
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
 
-use log::{info, trace};
-use cgmath::{Vector3, Rad, Matrix4, Point3, Deg};
+use log::{error, info, trace};
+use cgmath::{Rad, Matrix4, Euler};
 use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowBuilder;
 use winit::event;
 use raw_window_handle::HasRawWindowHandle;
 
-use crate::input;
+use crate::input::{self, Action, ActionState, Bindings, Recording};
+use crate::bounds::Bounds;
+use crate::pick::Ray;
+use crate::hud::Hud;
+use crate::Error;
 
 mod show;
-mod camera;
+mod config;
+mod app;
+mod viewpoint;
+mod turntable;
+pub mod camera;
 
-use camera::{View, Perspective, Camera};
+use camera::Camera;
 
-#[derive(Debug, Copy, Clone)]
+pub use config::{RunConfig, ConfigError};
+pub use app::{Application, Control, FrameTime};
+pub use viewpoint::Viewpoint;
+pub use turntable::{Turntable, SpinAxis};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Rot {
     x: Rad<f32>,
     y: Rad<f32>,
@@ -24,6 +40,11 @@ impl Rot {
     pub fn new(x: Rad<f32>, y: Rad<f32>, z: Rad<f32>) -> Self {
         Rot { x, y, z }
     }
+
+    /// As a rotation matrix for the scene.
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from(Euler::new(self.x, self.y, self.z))
+    }
 }
 
 impl Default for Rot {
@@ -32,17 +53,51 @@ impl Default for Rot {
     }
 }
 
+/// Size of the geometry being rendered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MeshStats {
+    pub vertices: usize,
+    pub triangles: usize,
+}
+
 /// All types that want to be shown must implement this trait. This must be the result of
 /// calling `init` from implementing the `Initializable` trait.
 pub trait Renderable {
+    /// Bounds of the geometry being rendered. Used to frame the camera.
+    fn bounds(&self) -> &Bounds;
+
     //fn resize(&mut self, desc: &wgpu::SwapChainDescriptor, device: &mut wgpu::Device);
     fn render(
         &mut self,
-        projection: &Matrix4<f32>,
+        camera: &Camera<f32>,
         rotation: &Matrix4<f32>,
         frame: &wgpu::SwapChainOutput,
         device: &mut wgpu::Device,
     );
+
+    /// Switch to the next way of shading when there's more than one. Returns the name of
+    /// the shading now in use.
+    fn cycle_shading(&mut self) -> Option<&str> {
+        None
+    }
+
+    /// Size of the geometry for the HUD when it's known.
+    fn statistics(&self) -> Option<MeshStats> {
+        None
+    }
+
+    /// Number of the face the ray hits first, with the ray in the geometry's own space.
+    /// Nothing can be selected unless this is implemented.
+    fn pick(&self, _ray: &Ray) -> Option<u32> {
+        None
+    }
+}
+
+/// How frames are to be drawn beyond what's in the `wgpu::SwapChainDescriptor`.
+#[derive(Debug, Copy, Clone)]
+pub struct Target {
+    pub clear_colour: wgpu::Color,
+    pub sample_count: u32,
 }
 
 /// All types that want to be rendered must be convertible via this trait into a
@@ -52,97 +107,342 @@ pub trait Initializable {
     type Ready;
     
     fn init(
-        self, desc: &wgpu::SwapChainDescriptor, device: &mut wgpu::Device
-    ) -> Self::Ready;
+        self, desc: &wgpu::SwapChainDescriptor, target: &Target, device: &mut wgpu::Device
+    ) -> Result<Self::Ready, Error>;
 }
 
 trait Presentation {
-    fn update(&mut self, movement: Vector3<f32>, rot: Rot) -> (&View<f32>, &Rot);    
+    fn viewpoint_mut(&mut self) -> &mut Viewpoint;
     fn present_frame(&mut self, frame: &wgpu::SwapChainOutput, device: &mut wgpu::Device);
 }
 
+/// Carry out what input did to the viewpoint over a frame that lasted `elapsed`, then spin
+/// the turntable if there wasn't any. Returns the triggers fired and the nudges since the
+/// last frame for the caller to pass on.
+fn steer<T: ActionState>(
+    state: &mut T, bindings: &Bindings, elapsed: Duration, viewpoint: &mut Viewpoint,
+) -> (Vec<Action>, Vec<(Action, f32)>) {
+    let triggered = state.take_triggered();
+    for action in triggered.iter() {
+        match action {
+            Action::ToggleProjection => {
+                let projection = viewpoint.toggle_projection();
+                info!("Switched to {:?} projection.", projection);
+            },
+            Action::ResetView => {
+                let view = viewpoint.reset_view();
+                trace!("Reset to {:?}", view);
+            },
+            Action::ToggleTurntable => {
+                let on = viewpoint.toggle_turntable();
+                info!("Turntable {}.", if on { "on" } else { "off" });
+            },
+            _ => (),
+        }
+    }
+
+    // Mouse motion and the wheel add to the held movement.
+    let nudged = state.take_nudged();
+    if !state.is_idle() || !nudged.is_empty() {
+        let increment = input::frame_increment(&*state, bindings, elapsed)
+            + input::nudge_increment(&nudged);
+        let rot = Rot::new(increment.x, increment.y, increment.z);
+        let (view, rot) = viewpoint.update(increment.camera, increment.camera_roll, rot);
+        trace!("{:?} && {:?}", view, rot);
+    }
+
+    // Toggling the turntable on shouldn't count as the input that pauses it, nor should
+    // showing the HUD.
+    let input = !state.is_idle()
+        || !nudged.is_empty()
+        || triggered
+            .iter()
+            .any(|action| !matches!(action, Action::ToggleTurntable | Action::ToggleHud));
+    viewpoint.turn(elapsed, input);
+
+    (triggered, nudged)
+}
+
+/// Play a recording back without a window, starting from `viewpoint`. Returns the camera
+/// and scene rotation after each frame, the same as a run playing it back would show them.
+///
+/// The viewpoint should match the one the recorded run started with: its camera after
+/// framing the scene and fitting the window's aspect ratio, and the same turntable.
+pub fn replay(
+    recording: &Recording, bindings: &Bindings, mut viewpoint: Viewpoint,
+) -> Vec<(Camera<f32>, Rot)> {
+    let mut act_state = input::ActionSet::new();
+    let mut held = input::HeldInput::default();
+
+    recording
+        .frames()
+        .iter()
+        .map(|frame| {
+            for event in frame.events.iter() {
+                event.apply(bindings, &mut held, &mut act_state);
+            }
+            steer(&mut act_state, bindings, frame.elapsed, &mut viewpoint);
+            (viewpoint.camera(), viewpoint.rotation())
+        })
+        .collect()
+}
+
+/// Present the scene with no application logic of its own.
+pub fn run<T>(config: RunConfig, scene: T) -> Result<(), Error>
+where T: Initializable,
+      T::Ready: Renderable + 'static,
+{
+    run_app(config, scene, ())
+}
+
+/// Ask wgpu for something it panics rather than fails to provide, returning `unavailable`
+/// instead of the panic.
+fn request<T, F: FnOnce() -> T>(request: F, unavailable: Error) -> Result<T, Error> {
+    panic::catch_unwind(AssertUnwindSafe(request)).map_err(|_| unavailable)
+}
+
+/// Present the scene while driving the application hooks every frame. Fails with
+/// `Error::AdapterUnavailable` when no video adapter matches the configured power
+/// preference and `Error::DeviceUnavailable` when the adapter can't provide a device.
+///
 /// Taken heavily from the examples in wgpu crate. I have no idea otherwise how to use.
-pub fn run<T>(title: &str, scene: T) -> Result<(), Box<dyn std::error::Error>>
+pub fn run_app<T, A>(
+    config: RunConfig, scene: T, mut app: A,
+) -> Result<(), Error>
 where T: Initializable,
       T::Ready: Renderable + 'static,
+      A: Application<T::Ready> + 'static,
 {
+    config.validate()?;
+
     info!("Setting up the window.");
     let event_loop = EventLoop::new();
-    let window = winit::window::Window::new(&event_loop)?;    
-    window.set_title(title);
+    let mut builder = WindowBuilder::new().with_title(config.title.as_str());
+    if let Some(size) = config.size {
+        builder = builder.with_inner_size(size);
+    }
+    if config.fullscreen {
+        builder = builder.with_fullscreen(Some(event_loop.primary_monitor()));
+    }
+    let window = builder.build(&event_loop)?;
+    if let (Some(position), false) = (config.position, config.fullscreen) {
+        window.set_outer_position(position);
+    }
+    let logical_size = window.inner_size();
     let hidpi_factor = window.hidpi_factor();
-    let w_size = window
-        .inner_size()
-        .to_physical(hidpi_factor);
+    let w_size = logical_size.to_physical(hidpi_factor);
     let w_width = w_size.width.round() as f32;
     let w_height = w_size.height.round() as f32;
     let instance = wgpu::Instance::new();
     let surface = instance.create_surface(window.raw_window_handle());
 
-    info!("Initializing the renderer.");    
-    let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::LowPower,
-    });
-    
-    let mut device = adapter.request_device(&wgpu::DeviceDescriptor {
-        extensions: wgpu::Extensions {
-            anisotropic_filtering: false,
-        },
-        limits: wgpu::Limits::default(),
-    });
+    // wgpu 0.3 has no fallible way to ask for these. Both panic when no adapter matches or
+    // it can't provide a device.
+    info!("Initializing the renderer.");
+    let adapter = request(
+        || instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: config.power_preference,
+        }),
+        Error::AdapterUnavailable(config.power_preference),
+    )?;
 
-    //                                                                       [View Dist].
-    let perspective = Perspective::new(Deg(45f32), w_width / w_height, 1f32, 100f32);
-    let view = View::new(
-        Point3::new(0f32, -4f32, 4f32), Point3::new(0f32, 0f32, 0f32), -Vector3::unit_z()
-    );
-    let camera = Camera::new(perspective, view);
-    
-    let bindings = input::Bindings::default();
-    let mut act_state: u16 = 0;
+    let mut device = request(
+        || adapter.request_device(&wgpu::DeviceDescriptor {
+            extensions: wgpu::Extensions {
+                anisotropic_filtering: false,
+            },
+            limits: wgpu::Limits::default(),
+        }),
+        Error::DeviceUnavailable,
+    )?;
+
+    config.camera.update(|camera| camera.set_aspect_ratio(w_width / w_height));
+
+    let bindings = config.bindings.clone();
+    let mut act_state = input::ActionSet::new();
+    let mut held = input::HeldInput::default();
+    let record_to = config.record;
+    let mut recording = record_to.as_ref().map(|_| Recording::new());
+    let mut playback = config.playback.map(|r| r.into_frames().into_iter());
 
     let desc = wgpu::SwapChainDescriptor {
         usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-        format: wgpu::TextureFormat::Bgra8Unorm,
+        format: config.surface_format,
         width: w_width as u32,
         height: w_height as u32,
-        present_mode: wgpu::PresentMode::Vsync,
+        present_mode: config.present_mode(),
+    };
+    let target = Target {
+        clear_colour: config.clear_colour,
+        sample_count: config.sample_count,
     };
     let mut swap_chain = device.create_swap_chain(&surface, &desc);
 
     info!("Initializing the scene.");
-    let mut show = show::Show::new(scene.init(&desc, &mut device), camera);
+    let hud = Hud::new(&desc, &mut device)?;
+    let mut show = show::Show::new(
+        scene.init(&desc, &target, &mut device)?,
+        config.camera,
+        config.frame_camera,
+        config.turntable,
+    )
+    .hud(hud, config.hud);
 
     info!("Entering event loop.");
-    event_loop.run(move |event, _, control_flow| match event {
-        event::Event::WindowEvent { event, .. } => match event {
-            event::WindowEvent::KeyboardInput {
-                input: event::KeyboardInput {
-                    virtual_keycode: Some(event::VirtualKeyCode::Escape),
-                    state: event::ElementState::Pressed,
-                    ..
-                },
-                ..
-            }
-            | event::WindowEvent::CloseRequested => {
-                *control_flow = ControlFlow::Exit;
+    let mut last_frame = Instant::now();
+    let mut total = Duration::from_secs(0);
+    let mut frame_count = 0u64;
+    let mut control = Control::default();
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            event::Event::WindowEvent { event, .. } => {
+                app.on_event(&event, &mut control);
+
+                match event {
+                    event::WindowEvent::KeyboardInput {
+                        input: event::KeyboardInput {
+                            virtual_keycode: Some(event::VirtualKeyCode::Escape),
+                            state: event::ElementState::Pressed,
+                            ..
+                        },
+                        ..
+                    }
+                    | event::WindowEvent::CloseRequested => {
+                        control.exit();
+                    },
+                    // Live input is ignored while playing back.
+                    _ if playback.is_some() => (),
+                    event => {
+                        if let Some(input) = input::InputEvent::from_window_event(&event) {
+                            input.apply(&bindings, &mut held, &mut act_state);
+                            if let Some(recording) = recording.as_mut() {
+                                recording.record(input);
+                            }
+                        }
+                    },
+                }
             },
-            event::WindowEvent::KeyboardInput { input: keyboard_input, .. } => {
-                let maybie = input::handle_keyboard(
-                    &keyboard_input, &bindings, &mut act_state
+            event::Event::EventsCleared => {
+                let now = Instant::now();
+                let mut elapsed = now - last_frame;
+                last_frame = now;
+
+                // Played back frames take the place of live input and timing.
+                if let Some(frames) = playback.as_mut() {
+                    match frames.next() {
+                        Some(frame) => {
+                            for input in frame.events {
+                                input.apply(&bindings, &mut held, &mut act_state);
+                                if let Some(recording) = recording.as_mut() {
+                                    recording.record(input);
+                                }
+                            }
+                            elapsed = frame.elapsed;
+                        },
+                        None => {
+                            info!("Playback finished.");
+                            playback = None;
+                        },
+                    }
+                }
+                if let Some(recording) = recording.as_mut() {
+                    recording.end_frame(elapsed);
+                }
+
+                total += elapsed;
+                let time = FrameTime { elapsed, total, frame: frame_count };
+
+                // Resetting the view frames whatever the application last set to be shown.
+                show.follow_bounds();
+                let (triggered, nudged) = steer(
+                    &mut act_state, &bindings, elapsed, show.viewpoint_mut(),
                 );
-                if let Some((camera_movement, rot_x, rot_y)) = maybie {
-                    let rot = Rot::new(rot_x, rot_y, Rad(0.0));
-                    let (view, rot) = show.update(camera_movement, rot);
-                    trace!("{:?} && {:?}", view, rot);
+
+                // The application sees the built in triggers too, after they've been
+                // carried out.
+                for action in triggered {
+                    match action {
+                        Action::CycleShading => {
+                            if let Some(shading) = show.scene_mut().cycle_shading() {
+                                info!("Switched to {} shading.", shading);
+                            }
+                        },
+                        Action::ToggleHud => {
+                            let shown = show.toggle_hud();
+                            info!("HUD {}.", if shown { "shown" } else { "hidden" });
+                        },
+                        Action::SelectFace => {
+                            let size = (logical_size.width, logical_size.height);
+                            let face = held.cursor().and_then(|c| show.pick(c, size));
+                            info!("Selected face {:?}.", face);
+                            control.select_face(face);
+                        },
+                        _ => (),
+                    }
+                    app.on_trigger(action, show.scene_mut(), &mut control);
+                }
+
+                for action in act_state.held().into_iter().filter(Action::is_custom) {
+                    app.on_held(action, &time, show.scene_mut(), &mut control);
+                }
+
+                for (action, amount) in nudged.into_iter().filter(|(a, _)| a.is_custom()) {
+                    app.on_axis(action, amount, show.scene_mut(), &mut control);
+                }
+
+                app.update(&time, show.scene_mut(), &mut control);
+
+                if !control.exit_requested() {
+                    show.update_hud(&time, control.selected_face());
+                    let frame = swap_chain.get_next_texture();
+                    show.present_frame(&frame, &mut device);
+                    frame_count += 1;
                 }
             },
+            event::Event::LoopDestroyed => {
+                info!("Exiting event loop.");
+                let saving = (recording.as_ref(), record_to.as_ref());
+                if let (Some(recording), Some(path)) = saving {
+                    match recording.save(path) {
+                        Ok(()) => info!("Saved input recording to {}.", path.display()),
+                        Err(e) => error!("Can't save input recording. {}", e),
+                    }
+                }
+                app.on_exit();
+            },
             _ => (),
-        },
-        event::Event::EventsCleared => {
-            let frame = swap_chain.get_next_texture();
-            show.present_frame(&frame, &mut device);
-        },
-        _ => (),
+        }
+
+        if control.exit_requested() {
+            *control_flow = ControlFlow::Exit;
+        }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panicking_request_is_unavailable() {
+        let preference = wgpu::PowerPreference::HighPerformance;
+        let adapter: Result<(), _> = request(
+            || panic!("No adapter."), Error::AdapterUnavailable(preference),
+        );
+        assert!(matches!(
+            adapter,
+            Err(Error::AdapterUnavailable(wgpu::PowerPreference::HighPerformance))
+        ));
+
+        let device: Result<(), _> = request(
+            || panic!("No device."), Error::DeviceUnavailable,
+        );
+        assert!(matches!(device, Err(Error::DeviceUnavailable)));
+    }
+
+    #[test]
+    fn request_that_returns_is_kept() {
+        let device = request(|| 3, Error::DeviceUnavailable);
+        assert!(matches!(device, Ok(3)));
+    }
+}
@@ -0,0 +1,81 @@
+//! Hooks for applications to run their own logic inside the presentation loop.
+use std::time::Duration;
+
+use winit::event::WindowEvent;
+
+use crate::input::Action;
+
+/// Timing of the frame about to be presented.
+#[derive(Debug, Copy, Clone)]
+pub struct FrameTime {
+    /// Time since the previous frame.
+    pub elapsed: Duration,
+    /// Time since the event loop started.
+    pub total: Duration,
+    /// Number of frames presented so far.
+    pub frame: u64,
+}
+
+/// Passed into the hooks so they can steer the presentation loop.
+#[derive(Debug, Default)]
+pub struct Control {
+    exit: bool,
+    selected_face: Option<u32>,
+}
+
+impl Control {
+    /// Stop the presentation loop after the current hook returns. `on_exit` is still
+    /// called.
+    pub fn exit(&mut self) {
+        self.exit = true;
+    }
+
+    pub fn exit_requested(&self) -> bool {
+        self.exit
+    }
+
+    /// Show this face as selected in the HUD, or no selection with `None`. Faces are
+    /// numbered in index order from 0. `Action::SelectFace` selects the face under the
+    /// cursor before `on_trigger` is called, so applications can replace the selection.
+    pub fn select_face(&mut self, face: Option<u32>) {
+        self.selected_face = face;
+    }
+
+    pub fn selected_face(&self) -> Option<u32> {
+        self.selected_face
+    }
+}
+
+/// Application logic that's driven by the presentation loop. All hooks default to doing
+/// nothing so only the ones needed have to be implemented.
+pub trait Application<T> {
+    /// Called once every frame before presenting with the ready scene.
+    fn update(&mut self, _time: &FrameTime, _scene: &mut T, _control: &mut Control) {}
+
+    /// Called once for every trigger action fired, built in or registered, before `update`.
+    fn on_trigger(&mut self, _action: Action, _scene: &mut T, _control: &mut Control) {}
+
+    /// Called every frame before `update` for each registered action that's held.
+    fn on_held(
+        &mut self,
+        _action: Action,
+        _time: &FrameTime,
+        _scene: &mut T,
+        _control: &mut Control,
+    ) {}
+
+    /// Called every frame before `update` for each registered action nudged by a mouse
+    /// axis, with the amount nudged since the last frame.
+    fn on_axis(
+        &mut self, _action: Action, _amount: f32, _scene: &mut T, _control: &mut Control,
+    ) {}
+
+    /// Called with every window event before the presentation handles it.
+    fn on_event(&mut self, _event: &WindowEvent, _control: &mut Control) {}
+
+    /// Called once when the presentation loop is exiting.
+    fn on_exit(&mut self) {}
+}
+
+/// No application logic. Used by `run`.
+impl<T> Application<T> for () {}
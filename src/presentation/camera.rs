@@ -1,6 +1,10 @@
 //! Perspective handling and viewport.
+use std::cell::Cell;
+use std::rc::Rc;
 
-use cgmath::{BaseFloat, Rad, Vector3, Point3, Matrix4};
+use cgmath::{
+    BaseFloat, Rad, Vector3, Point3, Matrix3, Matrix4, Angle, MetricSpace, InnerSpace,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Perspective<S: BaseFloat> {
@@ -18,6 +22,59 @@ impl<S: BaseFloat>  Perspective<S> {
     pub fn as_matrix(&self) -> Matrix4<S> {
         cgmath::perspective(self.fov, self.aspect_ratio, self.near, self.far)
     }
+
+    /// Vertical field of view.
+    pub fn fov(&self) -> Rad<S> {
+        self.fov
+    }
+
+    pub fn aspect_ratio(&self) -> S {
+        self.aspect_ratio
+    }
+
+    pub fn near(&self) -> S {
+        self.near
+    }
+
+    pub fn far(&self) -> S {
+        self.far
+    }
+
+    /// Half the height of the view frustum at `distance` from the eye.
+    pub fn half_height_at(&self, distance: S) -> S {
+        distance * (self.fov / (S::one() + S::one())).tan()
+    }
+}
+
+/// Parallel projection. The view volume is a box centered on the view axis.
+#[derive(Debug, Copy, Clone)]
+pub struct Orthographic<S: BaseFloat> {
+    half_height: S,
+    aspect_ratio: S,
+    near: S,
+    far: S,
+}
+
+impl<S: BaseFloat> Orthographic<S> {
+    pub fn new(half_height: S, aspect_ratio: S, near: S, far: S) -> Self {
+        Orthographic { half_height, aspect_ratio, near, far }
+    }
+
+    pub fn as_matrix(&self) -> Matrix4<S> {
+        let half_width = self.half_height * self.aspect_ratio;
+        cgmath::ortho(
+            -half_width, half_width,
+            -self.half_height, self.half_height,
+            self.near, self.far,
+        )
+    }
+}
+
+/// Which projection the `Camera` is currently using.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Projection {
+    Perspective,
+    Orthographic,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -39,26 +96,207 @@ impl<S: BaseFloat> View<S> {
     pub fn move_camera(&mut self, increment: Vector3<S>) {
         self.from += increment;
     }
+
+    /// Turn the up direction around the direction being looked in. Does nothing when the
+    /// eye is on the target as there's no direction to turn around.
+    pub fn roll(&mut self, angle: Rad<S>) {
+        if self.from == self.at {
+            return;
+        }
+        let axis = (self.at - self.from).normalize();
+        self.up = Matrix3::from_axis_angle(axis, angle) * self.up;
+    }
+
+    /// Eye position.
+    pub fn from(&self) -> Point3<S> {
+        self.from
+    }
+
+    /// Target being looked at.
+    pub fn at(&self) -> Point3<S> {
+        self.at
+    }
+
+    pub fn up(&self) -> Vector3<S> {
+        self.up
+    }
+
+    /// Distance from the eye to the point being looked at.
+    pub fn distance(&self) -> S {
+        self.from.distance(self.at)
+    }
 }
 
+/// Holds both projections so switching between them keeps the view intact.
 #[derive(Debug, Copy, Clone)]
 pub struct Camera<S: BaseFloat> {
     perspective: Perspective<S>,
+    orthographic: Orthographic<S>,
+    projection: Projection,
     view: View<S>,
 }
 
 impl<S: BaseFloat> Camera<S> {
+    /// The orthographic view volume starts out matching what the perspective sees at the
+    /// point being looked at, until it's fitted to the scene with `frame` or
+    /// `fit_orthographic`.
     pub fn new(perspective: Perspective<S>, view: View<S>) -> Self {
-        Camera { perspective, view }
+        let orthographic = Orthographic::new(
+            perspective.half_height_at(view.distance()),
+            perspective.aspect_ratio,
+            perspective.near,
+            perspective.far,
+        );
+
+        Camera { perspective, orthographic, projection: Projection::Perspective, view }
     }
 
     pub fn projection(&self) -> Matrix4<S> {
-        self.perspective.as_matrix() * self.view.as_matrix()
+        let projection = match self.projection {
+            Projection::Perspective => self.perspective.as_matrix(),
+            Projection::Orthographic => self.orthographic.as_matrix(),
+        };
+
+        projection * self.view.as_matrix()
+    }
+
+    pub fn view(&self) -> &View<S> {
+        &self.view
+    }
+
+    /// Replace the view. The current projection is kept.
+    pub fn set_view(&mut self, view: View<S>) {
+        self.view = view;
+    }
+
+    pub fn perspective(&self) -> &Perspective<S> {
+        &self.perspective
+    }
+
+    /// Replace the perspective. The orthographic view volume follows along.
+    pub fn set_perspective(&mut self, perspective: Perspective<S>) {
+        self.perspective = perspective;
+        self.orthographic.aspect_ratio = perspective.aspect_ratio;
+        self.orthographic.near = perspective.near;
+        self.orthographic.far = perspective.far;
+    }
+
+    /// Used to match the window shape.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: S) {
+        self.perspective.aspect_ratio = aspect_ratio;
+        self.orthographic.aspect_ratio = aspect_ratio;
+    }
+
+    /// Near and far planes. Both projections share them.
+    pub fn depth_range(&self) -> (S, S) {
+        (self.perspective.near, self.perspective.far)
+    }
+
+    /// Which projection is currently in use.
+    pub fn projection_mode(&self) -> Projection {
+        self.projection
     }
 
     /// Move the camera position by the supplied increment and return a ref to the view.
+    /// While orthographic, moving towards or away from the target shrinks or grows the view
+    /// volume in proportion so it zooms as the perspective would.
     pub fn move_camera(&mut self, increment: Vector3<S>) -> &View<S> {
+        let distance = self.view.distance();
+        if self.projection == Projection::Orthographic && distance > S::zero() {
+            let forward = increment.dot((self.view.at - self.view.from) / distance);
+            let remaining = distance - forward;
+            if remaining > S::zero() {
+                self.orthographic.half_height = self.orthographic.half_height * remaining
+                    / distance;
+            }
+        }
+
         self.view.move_camera(increment);
         &self.view
     }
+
+    /// Roll the camera around the direction it's looking in and return a ref to the view.
+    pub fn roll_camera(&mut self, angle: Rad<S>) -> &View<S> {
+        self.view.roll(angle);
+        &self.view
+    }
+
+    /// Switch to the supplied projection. The view is kept as is.
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    /// Size the orthographic view volume so a sphere of `radius` around the target just
+    /// fits the narrower of its width and height.
+    pub fn fit_orthographic(&mut self, radius: S) {
+        self.orthographic.half_height = if self.orthographic.aspect_ratio < S::one() {
+            radius / self.orthographic.aspect_ratio
+        } else {
+            radius
+        };
+    }
+
+    /// Flip between perspective and orthographic. Returns the projection now in use.
+    pub fn toggle_projection(&mut self) -> Projection {
+        let projection = match self.projection {
+            Projection::Perspective => Projection::Orthographic,
+            Projection::Orthographic => Projection::Perspective,
+        };
+        self.set_projection(projection);
+        projection
+    }
+
+    /// Look at `center` from along the current viewing direction, backing off until the
+    /// sphere of `radius` fits the narrower of the two fields of view. The near and far
+    /// planes are fitted around the sphere with some slack for moving the camera, and the
+    /// orthographic view volume around the sphere itself.
+    pub fn frame(&mut self, center: Point3<S>, radius: S) {
+        let two = S::one() + S::one();
+        let half_fov = self.perspective.fov / two;
+        let half_fov = if self.perspective.aspect_ratio < S::one() {
+            Rad::atan(half_fov.tan() * self.perspective.aspect_ratio)
+        } else {
+            half_fov
+        };
+        let distance = radius / half_fov.sin();
+
+        let direction = (self.view.from - self.view.at).normalize();
+        self.view.at = center;
+        self.view.from = center + direction * distance;
+
+        self.perspective.near = (distance - radius) / two;
+        self.perspective.far = (distance + radius) * two;
+        self.orthographic.near = self.perspective.near;
+        self.orthographic.far = self.perspective.far;
+
+        self.fit_orthographic(radius);
+    }
+}
+
+/// Shared handle to the camera of a running presentation. The event loop takes ownership of
+/// everything passed to `run`, so keep a clone of this to query or move the live camera
+/// from application code.
+#[derive(Debug, Clone)]
+pub struct CameraHandle(Rc<Cell<Camera<f32>>>);
+
+impl CameraHandle {
+    pub fn new(camera: Camera<f32>) -> Self {
+        CameraHandle(Rc::new(Cell::new(camera)))
+    }
+
+    pub fn get(&self) -> Camera<f32> {
+        self.0.get()
+    }
+
+    pub fn set(&self, camera: Camera<f32>) {
+        self.0.set(camera)
+    }
+
+    /// Modify the camera in place and return the result.
+    pub fn update<F: FnOnce(&mut Camera<f32>)>(&self, f: F) -> Camera<f32> {
+        let mut camera = self.0.get();
+        f(&mut camera);
+        self.0.set(camera);
+        camera
+    }
 }
@@ -0,0 +1,211 @@
+//! Configuration of a presentation before it's run.
+use std::path::PathBuf;
+use std::{error, fmt};
+
+use cgmath::{Deg, Point3, Vector3};
+use winit::dpi::{LogicalPosition, LogicalSize};
+
+use crate::Error;
+use crate::input::{Bindings, Recording};
+use super::camera::{Camera, CameraHandle, Perspective, View};
+use super::turntable::Turntable;
+
+/// Swap chain formats that every wgpu backend can present.
+const SURFACE_FORMATS: [wgpu::TextureFormat; 2] = [
+    wgpu::TextureFormat::Bgra8Unorm,
+    wgpu::TextureFormat::Bgra8UnormSrgb,
+];
+
+/// MSAA sample counts that every wgpu backend must support for colour and depth targets.
+const SAMPLE_COUNTS: [u32; 2] = [1, 4];
+
+/// An option in the `RunConfig` can't be used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    WindowSize(f64, f64),
+    SurfaceFormat(wgpu::TextureFormat),
+    SampleCount(u32),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::WindowSize(w, h) => write!(f, "Invalid window size {}x{}.", w, h),
+            ConfigError::SurfaceFormat(format) => write!(
+                f, "Surface format {:?} unsupported. Use one of {:?}.",
+                format, SURFACE_FORMATS,
+            ),
+            ConfigError::SampleCount(count) => write!(
+                f, "Sample count {} unsupported. Use one of {:?}.", count, SAMPLE_COUNTS,
+            ),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// Options for `run`. Built by chaining from `RunConfig::new`.
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    pub(super) title: String,
+    pub(super) camera: CameraHandle,
+    pub(super) frame_camera: bool,
+    pub(super) size: Option<LogicalSize>,
+    pub(super) position: Option<LogicalPosition>,
+    pub(super) fullscreen: bool,
+    pub(super) vsync: bool,
+    pub(super) power_preference: wgpu::PowerPreference,
+    pub(super) surface_format: wgpu::TextureFormat,
+    pub(super) clear_colour: wgpu::Color,
+    pub(super) sample_count: u32,
+    pub(super) bindings: Bindings,
+    pub(super) record: Option<PathBuf>,
+    pub(super) playback: Option<Recording>,
+    pub(super) turntable: Turntable,
+    pub(super) hud: bool,
+}
+
+impl RunConfig {
+    /// Defaults to a 45 degree perspective camera looking down on the origin, backed off to
+    /// frame the scene. The window is left to the platform to size and place. Frames are
+    /// presented with vsync, cleared to black and drawn without multisampling on a low
+    /// power adapter. Keys are bound as in `Bindings::default`. The turntable and HUD are
+    /// off until toggled.
+    pub fn new(title: &str) -> Self {
+        let perspective = Perspective::new(Deg(45f32), 1f32, 1f32, 100f32);
+        let view = View::new(
+            Point3::new(0f32, -4f32, 4f32),
+            Point3::new(0f32, 0f32, 0f32),
+            -Vector3::unit_z(),
+        );
+
+        RunConfig {
+            title: title.to_owned(),
+            camera: CameraHandle::new(Camera::new(perspective, view)),
+            frame_camera: true,
+            size: None,
+            position: None,
+            fullscreen: false,
+            vsync: true,
+            power_preference: wgpu::PowerPreference::LowPower,
+            surface_format: wgpu::TextureFormat::Bgra8Unorm,
+            clear_colour: wgpu::Color::BLACK,
+            sample_count: 1,
+            bindings: Bindings::default(),
+            record: None,
+            playback: None,
+            turntable: Turntable::default(),
+            hud: false,
+        }
+    }
+
+    /// Start with this camera as is instead of framing the scene. Only the aspect ratio is
+    /// replaced to match the window and the orthographic view volume fitted to the scene.
+    /// This is also where resetting the view returns to.
+    pub fn camera(self, camera: Camera<f32>) -> Self {
+        self.camera.set(camera);
+        RunConfig { frame_camera: false, ..self }
+    }
+
+    /// Handle to the camera that will be used once running.
+    pub fn camera_handle(&self) -> CameraHandle {
+        self.camera.clone()
+    }
+
+    /// Inner size of the window in logical pixels.
+    pub fn size(self, width: f64, height: f64) -> Self {
+        RunConfig { size: Some(LogicalSize::new(width, height)), ..self }
+    }
+
+    /// Outer position of the window in logical pixels.
+    pub fn position(self, x: f64, y: f64) -> Self {
+        RunConfig { position: Some(LogicalPosition::new(x, y)), ..self }
+    }
+
+    /// Fullscreen on the primary monitor. Overrides the size and position.
+    pub fn fullscreen(self, fullscreen: bool) -> Self {
+        RunConfig { fullscreen, ..self }
+    }
+
+    pub fn vsync(self, vsync: bool) -> Self {
+        RunConfig { vsync, ..self }
+    }
+
+    /// Kind of video adapter to ask for. `run` fails with `Error::AdapterUnavailable` when
+    /// there's none of the kind.
+    pub fn power_preference(self, power_preference: wgpu::PowerPreference) -> Self {
+        RunConfig { power_preference, ..self }
+    }
+
+    /// Swap chain format. Only the formats every backend can present are accepted.
+    pub fn surface_format(self, surface_format: wgpu::TextureFormat) -> Self {
+        RunConfig { surface_format, ..self }
+    }
+
+    pub fn clear_colour(self, clear_colour: wgpu::Color) -> Self {
+        RunConfig { clear_colour, ..self }
+    }
+
+    /// Number of MSAA samples per pixel. 1 disables multisampling. Only the counts every
+    /// backend must support are accepted.
+    pub fn sample_count(self, sample_count: u32) -> Self {
+        RunConfig { sample_count, ..self }
+    }
+
+    /// Replace the key bindings. Load them with `Bindings::load` to read them from a file.
+    pub fn bindings(self, bindings: Bindings) -> Self {
+        RunConfig { bindings, ..self }
+    }
+
+    /// Record input frame by frame and save it to this file on exit.
+    pub fn record<P: Into<PathBuf>>(self, path: P) -> Self {
+        RunConfig { record: Some(path.into()), ..self }
+    }
+
+    /// Play back recorded input in place of live input, one recorded frame per frame
+    /// presented. Live input takes over once the recording runs out. Only Escape and
+    /// closing the window are heeded while playing back.
+    pub fn playback(self, recording: Recording) -> Self {
+        RunConfig { playback: Some(recording), ..self }
+    }
+
+    /// How the scene spins when the turntable is on, and whether it starts on.
+    pub fn turntable(self, turntable: Turntable) -> Self {
+        RunConfig { turntable, ..self }
+    }
+
+    /// Start with the HUD shown. Either way it can be toggled with `Action::ToggleHud`.
+    pub fn hud(self, hud: bool) -> Self {
+        RunConfig { hud, ..self }
+    }
+
+    /// Check the options that don't need an adapter. wgpu 0.3 doesn't report what an
+    /// adapter supports, so formats and sample counts are checked against what every
+    /// backend is required to support. `run` calls this before it opens the window, then
+    /// checks the power preference by asking for an adapter with it.
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(size) = self.size {
+            if !(size.width >= 1.0 && size.height >= 1.0) {
+                return Err(ConfigError::WindowSize(size.width, size.height).into());
+            }
+        }
+
+        if !SURFACE_FORMATS.contains(&self.surface_format) {
+            return Err(ConfigError::SurfaceFormat(self.surface_format).into());
+        }
+
+        if !SAMPLE_COUNTS.contains(&self.sample_count) {
+            return Err(ConfigError::SampleCount(self.sample_count).into());
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn present_mode(&self) -> wgpu::PresentMode {
+        if self.vsync {
+            wgpu::PresentMode::Vsync
+        } else {
+            wgpu::PresentMode::NoVsync
+        }
+    }
+}
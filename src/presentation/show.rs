@@ -1,42 +1,173 @@
 //! Show something renderable.
 
-use cgmath::{Matrix4, Vector3, Euler};
+use std::time::Duration;
 
-use super::camera::{View, Camera};
-use super::{Rot, Presentation, Renderable};
+use cgmath::{Angle, Deg, Point3, Rad, EuclideanSpace};
 
-/// Compose the camera, scene rotation and scene.
+use crate::hud::Hud;
+use crate::input::Position;
+use crate::pick::Ray;
+use super::camera::CameraHandle;
+use super::turntable::Turntable;
+use super::viewpoint::Viewpoint;
+use super::{FrameTime, Presentation, Renderable};
+
+/// How long frame times are averaged over before the HUD shows the next figure. Showing
+/// every frame's time flickers too much to read.
+const FRAME_TIME_PERIOD: Duration = Duration::from_millis(500);
+
+/// Frame time averaged over a period.
+#[derive(Debug, Default)]
+struct FrameRate {
+    frames: u32,
+    time: Duration,
+    average: Option<Duration>,
+}
+
+impl FrameRate {
+    /// Count a frame. Returns the average over the last whole period, or this frame's time
+    /// until a period has passed.
+    fn tick(&mut self, elapsed: Duration) -> Duration {
+        self.frames += 1;
+        self.time += elapsed;
+        if self.time >= FRAME_TIME_PERIOD {
+            self.average = Some(self.time / self.frames);
+            self.frames = 0;
+            self.time = Duration::from_secs(0);
+        }
+        self.average.unwrap_or(elapsed)
+    }
+}
+
+/// Compose the viewpoint and scene.
 pub struct Show<T: Renderable> {
-    camera: Camera<f32>,
-    rotation: Rot,
+    viewpoint: Viewpoint,
     scene: T,
+    /// Whether the camera is framed around the scene, and the radius it was last fitted to.
+    frame: bool,
+    radius: f32,
+    hud: Option<Hud>,
+    hud_shown: bool,
+    hud_lines: Vec<String>,
+    frame_rate: FrameRate,
 }
 
 impl<T: Renderable> Show<T> {
-    pub fn new(scene: T, camera: Camera<f32>) -> Self {
+    /// When `frame` is set the camera is framed around the scene bounds first, otherwise
+    /// only the orthographic view volume is fitted to them. Either way the starting camera
+    /// is remembered for resetting the view, and refitted by `follow_bounds`.
+    pub fn new(scene: T, camera: CameraHandle, frame: bool, turntable: Turntable) -> Self {
+        let radius = scene.bounds().rotation_radius();
+        if frame {
+            camera.update(|c| c.frame(Point3::origin(), radius));
+        } else {
+            camera.update(|c| c.fit_orthographic(radius));
+        }
+
         Show {
-            camera,
-            rotation: Rot::default(),
+            viewpoint: Viewpoint::new(camera).turntable(turntable),
             scene,
+            frame,
+            radius,
+            hud: None,
+            hud_shown: false,
+            hud_lines: Vec::new(),
+            frame_rate: FrameRate::default(),
+        }
+    }
+
+    /// Refit the camera that resetting the view returns to when the scene's bounds have
+    /// changed, such as after new geometry was set. The live camera isn't moved.
+    pub fn follow_bounds(&mut self) {
+        let radius = self.scene.bounds().rotation_radius();
+        if radius != self.radius {
+            self.radius = radius;
+            self.viewpoint.refit_home(radius, self.frame);
+        }
+    }
+
+    /// Draw the HUD over the scene while it's shown.
+    pub fn hud(self, hud: Hud, shown: bool) -> Self {
+        Show { hud: Some(hud), hud_shown: shown, ..self }
+    }
+
+    pub fn scene_mut(&mut self) -> &mut T {
+        &mut self.scene
+    }
+
+    /// Show or hide the HUD. Returns whether it's now shown.
+    pub fn toggle_hud(&mut self) -> bool {
+        self.hud_shown = !self.hud_shown;
+        self.hud_shown
+    }
+
+    /// The face under the cursor, in logical pixels from the top left of a window of
+    /// `size`.
+    pub fn pick(&self, cursor: Position, size: (f64, f64)) -> Option<u32> {
+        let x = (cursor.x / size.0 * 2.0 - 1.0) as f32;
+        let y = (1.0 - cursor.y / size.1 * 2.0) as f32;
+        let transform = self.viewpoint.camera().projection()
+            * self.viewpoint.rotation().matrix();
+
+        self.scene.pick(&Ray::unproject(transform, x, y)?)
+    }
+
+    /// Fill in the HUD for the frame about to be presented.
+    pub fn update_hud(&mut self, time: &FrameTime, selected_face: Option<u32>) {
+        let frame_time = self.frame_rate.tick(time.elapsed);
+        if !self.hud_shown {
+            return;
         }
+
+        let seconds = frame_time.as_secs_f32();
+        let from = self.viewpoint.camera().view().from();
+        let rotation = self.viewpoint.rotation();
+        let degrees = |angle: Rad<f32>| Deg::from(angle).normalize().0;
+        let mesh = match self.scene.statistics() {
+            Some(stats) => {
+                format!("{} vertices, {} triangles", stats.vertices, stats.triangles)
+            },
+            None => "unknown".to_owned(),
+        };
+        let selected = match selected_face {
+            Some(face) => face.to_string(),
+            None => "none".to_owned(),
+        };
+
+        self.hud_lines = vec![
+            format!(
+                "Frame    {:.1} ms ({:.0} fps)",
+                seconds * 1000.0,
+                if seconds > 0.0 { seconds.recip() } else { 0.0 },
+            ),
+            format!("Camera   {:.2}, {:.2}, {:.2}", from.x, from.y, from.z),
+            format!(
+                "Rotation {:.1}, {:.1}, {:.1} deg",
+                degrees(rotation.x),
+                degrees(rotation.y),
+                degrees(rotation.z),
+            ),
+            format!("Mesh     {}", mesh),
+            format!("Selected {}", selected),
+        ];
     }
 }
 
 impl<T: Renderable> Presentation for Show<T> {
-    fn update(&mut self, movement: Vector3<f32>, rot_inc: Rot) -> (&View<f32>, &Rot) {
-        self.rotation.x += rot_inc.x;
-        self.rotation.y += rot_inc.y;
-        self.rotation.z += rot_inc.z;
-
-        (self.camera.move_camera(movement), &self.rotation)
+    fn viewpoint_mut(&mut self) -> &mut Viewpoint {
+        &mut self.viewpoint
     }
-    
+
     fn present_frame(&mut self, frame: &wgpu::SwapChainOutput, device: &mut wgpu::Device) {
         self.scene.render(
-            &self.camera.projection(),
-            &Matrix4::from(Euler::new(self.rotation.x, self.rotation.y, self.rotation.z)),
+            &self.viewpoint.camera(),
+            &self.viewpoint.rotation().matrix(),
             frame,
             device,
         );
+
+        if let (Some(hud), true) = (self.hud.as_mut(), self.hud_shown) {
+            hud.render(&self.hud_lines, frame, device);
+        }
     }
 }
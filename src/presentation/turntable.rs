@@ -0,0 +1,79 @@
+//! Spin the scene on its own for showing it off.
+use std::time::Duration;
+
+use cgmath::{Deg, Rad};
+
+use super::Rot;
+
+/// Which of the scene rotation angles the turntable turns.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpinAxis {
+    X,
+    Y,
+    Z,
+}
+
+/// Spins the scene at a steady speed around one of its rotation axes. Any input that moves
+/// the camera or scene, or fires an action, pauses the spin until there's been no input
+/// for `resume_after`.
+#[derive(Debug, Copy, Clone)]
+pub struct Turntable {
+    axis: SpinAxis,
+    speed: Rad<f32>,
+    resume_after: Duration,
+    enabled: bool,
+}
+
+impl Turntable {
+    /// Spin around `axis` at `speed` per second. Starts enabled and resumes after three
+    /// seconds without input.
+    pub fn new<A: Into<Rad<f32>>>(axis: SpinAxis, speed: A) -> Self {
+        Turntable {
+            axis,
+            speed: speed.into(),
+            resume_after: Duration::from_secs(3),
+            enabled: true,
+        }
+    }
+
+    pub fn resume_after(self, resume_after: Duration) -> Self {
+        Turntable { resume_after, ..self }
+    }
+
+    /// Whether to spin from the start. Either way it can be toggled with
+    /// `Action::ToggleTurntable`.
+    pub fn enabled(self, enabled: bool) -> Self {
+        Turntable { enabled, ..self }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(super) fn toggle(&mut self) -> bool {
+        self.enabled = !self.enabled;
+        self.enabled
+    }
+
+    pub(super) fn resumes_after(&self) -> Duration {
+        self.resume_after
+    }
+
+    /// The rotation over `elapsed`.
+    pub(super) fn increment(&self, elapsed: Duration) -> Rot {
+        let angle = self.speed * elapsed.as_secs_f32();
+        let none = Rad(0f32);
+        match self.axis {
+            SpinAxis::X => Rot::new(angle, none, none),
+            SpinAxis::Y => Rot::new(none, angle, none),
+            SpinAxis::Z => Rot::new(none, none, angle),
+        }
+    }
+}
+
+/// Disabled. Spins around the z axis at 30 degrees per second once toggled on.
+impl Default for Turntable {
+    fn default() -> Self {
+        Turntable::new(SpinAxis::Z, Deg(30f32)).enabled(false)
+    }
+}
@@ -0,0 +1,115 @@
+//! Where the scene is seen from, apart from the scene itself.
+
+use std::time::Duration;
+
+use cgmath::{EuclideanSpace, Point3, Rad, Vector3};
+
+use super::camera::{View, CameraHandle, Camera, Projection};
+use super::turntable::Turntable;
+use super::Rot;
+
+/// The camera and scene rotation that input steers, along with the camera to reset to and
+/// the turntable.
+#[derive(Debug, Clone)]
+pub struct Viewpoint {
+    home: Camera<f32>,
+    camera: CameraHandle,
+    rotation: Rot,
+    turntable: Turntable,
+    /// Time since the last input, for resuming the turntable.
+    since_input: Duration,
+}
+
+impl Viewpoint {
+    /// Starts from the camera as it is now, which is also where resetting returns to. The
+    /// turntable is off.
+    pub fn new(camera: CameraHandle) -> Self {
+        let turntable = Turntable::default();
+        Viewpoint {
+            home: camera.get(),
+            camera,
+            rotation: Rot::default(),
+            turntable,
+            since_input: turntable.resumes_after(),
+        }
+    }
+
+    pub fn turntable(self, turntable: Turntable) -> Self {
+        Viewpoint { turntable, since_input: turntable.resumes_after(), ..self }
+    }
+
+    pub fn camera(&self) -> Camera<f32> {
+        self.camera.get()
+    }
+
+    pub fn rotation(&self) -> Rot {
+        self.rotation
+    }
+
+    pub fn update(
+        &mut self, movement: Vector3<f32>, roll: Rad<f32>, rot_inc: Rot,
+    ) -> (View<f32>, &Rot) {
+        self.rotation.x += rot_inc.x;
+        self.rotation.y += rot_inc.y;
+        self.rotation.z += rot_inc.z;
+
+        let camera = self.camera.update(|c| {
+            c.move_camera(movement);
+            if roll != Rad(0.0) {
+                c.roll_camera(roll);
+            }
+        });
+        (*camera.view(), &self.rotation)
+    }
+
+    /// Switch the turntable on or off. Returns whether it's now on. It starts spinning
+    /// straight away when switched on.
+    pub fn toggle_turntable(&mut self) -> bool {
+        self.since_input = self.turntable.resumes_after();
+        self.turntable.toggle()
+    }
+
+    /// Spin the turntable for a frame that lasted `elapsed` unless there was input during
+    /// the frame or too recently before it.
+    pub fn turn(&mut self, elapsed: Duration, input: bool) {
+        if input {
+            self.since_input = Duration::from_secs(0);
+            return;
+        }
+
+        self.since_input += elapsed;
+        let idle = self.since_input >= self.turntable.resumes_after();
+        if self.turntable.is_enabled() && idle {
+            let spin = self.turntable.increment(elapsed);
+            self.rotation.x += spin.x;
+            self.rotation.y += spin.y;
+            self.rotation.z += spin.z;
+        }
+    }
+
+    pub fn toggle_projection(&mut self) -> Projection {
+        let camera = self.camera.update(|c| { c.toggle_projection(); });
+        camera.projection_mode()
+    }
+
+    /// Fit the camera that resetting returns to around geometry reaching `radius` from the
+    /// origin. When `frame` is set it's framed from the direction it looks in, otherwise
+    /// only its orthographic view volume is fitted. The live camera isn't moved.
+    pub fn refit_home(&mut self, radius: f32, frame: bool) {
+        if frame {
+            self.home.frame(Point3::origin(), radius);
+        } else {
+            self.home.fit_orthographic(radius);
+        }
+    }
+
+    /// Back to the starting camera and no rotation. The projection is kept.
+    pub fn reset_view(&mut self) -> View<f32> {
+        let projection = self.camera.get().projection_mode();
+        let mut camera = self.home;
+        camera.set_projection(projection);
+        self.camera.set(camera);
+        self.rotation = Rot::default();
+        *camera.view()
+    }
+}
@@ -1,16 +1,69 @@
 //! Typestate that holds render pipelines, perspectives and assets.
-use std::io;
+use std::{io, mem, slice};
 
-use num_traits::identities::Zero;
-use cgmath::Matrix4;
+use cgmath::{Matrix4, Point3};
 
 use gromit::{Geometry, Vertex};
 
-use crate::shader::CompiledShaders;
-use crate::presentation::{Initializable, Renderable};
+use crate::shader::{self, MAX_LIGHTS};
+use crate::shader::reflect;
+use crate::pipeline::{Binding, Layout, Pipeline, Uniform};
+use crate::presentation::{Initializable, MeshStats, Renderable, Target};
+use crate::presentation::camera::{Camera, Projection};
 use crate::light::{Light, LightRaw};
+use crate::bounds::Bounds;
+use crate::pick::{self, Ray};
+use crate::error::{Error, GeometryError};
 
-const MAX_LIGHTS: usize = 10;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Position, normal and colour, each a `Float3`.
+const VERTEX_SIZE: usize = 4 * 9;
+
+/// Shaders read from SPIR-V along with what they expect to be fed.
+#[derive(Clone)]
+struct Program {
+    vert: Vec<u32>,
+    frag: Vec<u32>,
+    layout: Layout,
+    cull_mode: wgpu::CullMode,
+}
+
+impl Program {
+    /// Read both shaders and check their interfaces match the layout.
+    fn new(
+        vert: &[u8], frag: &[u8], layout: Layout, cull_mode: wgpu::CullMode,
+    ) -> Result<Self, Error> {
+        let vert = wgpu::read_spirv(io::Cursor::new(vert)).map_err(Error::Spirv)?;
+        let frag = wgpu::read_spirv(io::Cursor::new(frag)).map_err(Error::Spirv)?;
+
+        reflect::validate(
+            &reflect::reflect(&vert)?,
+            &reflect::reflect(&frag)?,
+            &layout.locations(),
+            &layout.uniform_bindings(),
+        )?;
+
+        Ok(Program { vert, frag, layout, cull_mode })
+    }
+
+    fn from_pipeline<T: Pipeline>(pipeline: &T) -> Result<Self, Error> {
+        Program::new(
+            pipeline.vertex(), pipeline.fragment(), pipeline.layout(), pipeline.cull_mode(),
+        )
+    }
+}
+
+/// The scene's own shading followed by the built in debug shading to cycle through.
+fn shadings(program: Program) -> Result<Vec<(&'static str, Program)>, Error> {
+    Ok(vec![
+        ("scene", program),
+        ("normals", Program::from_pipeline(&shader::load_normal_shaders()?)?),
+        ("depth", Program::from_pipeline(&shader::load_depth_shaders()?)?),
+        ("wireframe", Program::from_pipeline(&shader::load_wireframe_shaders()?)?),
+        ("unlit", Program::from_pipeline(&shader::load_unlit_shaders()?)?),
+    ])
+}
 
 #[derive(Debug, Clone)]
 pub struct Cached {
@@ -33,32 +86,248 @@ impl Geometry for Cached {
     }
 }
 
+/// Check the geometry can be drawn as an indexed triangle list.
+fn validate(vertices: &[Vertex], index: &[u16]) -> Result<(), GeometryError> {
+    if vertices.is_empty() || index.is_empty() {
+        return Err(GeometryError::Empty);
+    }
+
+    if Vertex::sizeof() != VERTEX_SIZE {
+        return Err(GeometryError::VertexSize(Vertex::sizeof()));
+    }
+
+    if index.len() % 3 != 0 {
+        return Err(GeometryError::IndexCount(index.len()));
+    }
+
+    match index.iter().find(|i| **i as usize >= vertices.len()) {
+        Some(i) => Err(GeometryError::IndexOutOfRange {
+            index: *i, vertices: vertices.len(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Pull the position out of a vertex from the bytes the vertex buffer is filled with. The
+/// pipeline reads the position as the `Float3` at offset 0; `validate` has already checked
+/// the vertices are the nine floats it expects, and indexing keeps the read in bounds.
+fn vertex_position(vertex: &Vertex) -> Point3<f32> {
+    let size = mem::size_of::<Vertex>();
+    let bytes = unsafe {
+        slice::from_raw_parts(vertex as *const Vertex as *const u8, size)
+    };
+    let float = |i: usize| {
+        let mut word = [0u8; 4];
+        word.copy_from_slice(&bytes[i * 4..i * 4 + 4]);
+        f32::from_ne_bytes(word)
+    };
+    Point3::new(float(0), float(1), float(2))
+}
+
+/// Create a texture to draw into that matches the size of the swap chain frames. The
+/// texture is kept alongside its view so it lives as long as the view is used.
+fn attachment(
+    desc: &wgpu::SwapChainDescriptor,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    device: &mut wgpu::Device,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d { width: desc.width, height: desc.height, depth: 1 },
+        array_layer_count: 1,
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+    });
+    let view = texture.create_default_view();
+
+    (texture, view)
+}
+
+/// Vertex and index buffers for the geometry and how many to draw. Unindexed geometry has
+/// every triangle's vertices repeated in order. The index buffer is still made but unused.
+fn upload_geometry(
+    device: &mut wgpu::Device, vertices: &[Vertex], index: &[u16], unindexed: bool,
+) -> (wgpu::Buffer, wgpu::Buffer, usize) {
+    let vertex_buf = if unindexed {
+        let repeated: Vec<Vertex> = index
+            .iter()
+            .map(|i| vertices[*i as usize].clone())
+            .collect();
+        device
+            .create_buffer_mapped(repeated.len(), wgpu::BufferUsage::VERTEX)
+            .fill_from_slice(&repeated)
+    } else {
+        device
+            .create_buffer_mapped(vertices.len(), wgpu::BufferUsage::VERTEX)
+            .fill_from_slice(vertices)
+    };
+
+    let index_buf = device
+        .create_buffer_mapped(index.len(), wgpu::BufferUsage::INDEX)
+        .fill_from_slice(index);
+
+    (vertex_buf, index_buf, index.len())
+}
+
+/// Uniform buffers for each binding of the layout, zeroed, and the bind group over them.
+fn bind_uniforms(
+    device: &mut wgpu::Device, layout: &Layout,
+) -> (Vec<(Binding, wgpu::Buffer)>, wgpu::BindGroup, wgpu::PipelineLayout) {
+    let uniforms: Vec<(Binding, wgpu::Buffer)> = layout.bindings()
+        .iter()
+        .map(|binding| {
+            let zeroed = vec![0u8; binding.uniform.size() as usize];
+            let buffer = device
+                .create_buffer_mapped(
+                    zeroed.len(),
+                    wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                )
+                .fill_from_slice(&zeroed);
+            (*binding, buffer)
+        })
+        .collect();
+
+    let layout_bindings: Vec<wgpu::BindGroupLayoutBinding> = uniforms
+        .iter()
+        .map(|(binding, _)| wgpu::BindGroupLayoutBinding {
+            binding: binding.binding,
+            visibility: binding.visibility(),
+            ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+        })
+        .collect();
+
+    let bg_layout = device.create_bind_group_layout(
+        &wgpu::BindGroupLayoutDescriptor { bindings: &layout_bindings }
+    );
+
+    let pipeline_layout = device.create_pipeline_layout(
+        &wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&bg_layout], }
+    );
+
+    let bindings: Vec<wgpu::Binding> = uniforms
+        .iter()
+        .map(|(binding, buffer)| wgpu::Binding {
+            binding: binding.binding,
+            resource: wgpu::BindingResource::Buffer {
+                buffer,
+                range: 0..binding.uniform.size(),
+            },
+        })
+        .collect();
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &bg_layout,
+        bindings: &bindings,
+    });
+
+    (uniforms, bind_group, pipeline_layout)
+}
+
+/// Create the render pipeline for the program's vertex layout.
+fn create_pipeline(
+    device: &mut wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    program: &Program,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let m_vert = device.create_shader_module(&program.vert);
+    let m_frag = device.create_shader_module(&program.frag);
+    let attributes = program.layout.vertex_attributes();
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        layout,
+        vertex_stage: wgpu::ProgrammableStageDescriptor {
+            module: &m_vert,
+            entry_point: "main",
+        },
+        fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+            module: &m_frag,
+            entry_point: "main",
+        }),
+        rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+            front_face: wgpu::FrontFace::Cw,
+            cull_mode: program.cull_mode,
+            depth_bias: 2,
+            depth_bias_slope_scale: 2.0,
+            depth_bias_clamp: 0.0,
+        }),
+        primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+        color_states: &[wgpu::ColorStateDescriptor {
+            format,
+            color_blend: wgpu::BlendDescriptor::REPLACE,
+            alpha_blend: wgpu::BlendDescriptor::REPLACE,
+            write_mask: wgpu::ColorWrite::ALL,
+        }],
+        depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        }),
+        index_format: wgpu::IndexFormat::Uint16,
+        vertex_buffers: &[wgpu::VertexBufferDescriptor {
+            stride: Vertex::sizeof() as u64,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &attributes,
+        }],
+        sample_count,
+        sample_mask: !0,
+        alpha_to_coverage_enabled: false,
+    })
+}
+
 /// Begin construction of a new `Scene`.
 pub struct Begin;
 
 pub struct Lights {
-    frag: Vec<u32>,
-    vert: Vec<u32>,
+    shadings: Vec<(&'static str, Program)>,
     lights: Vec<Light>,
 }
 
-pub struct Prepare<T: Geometry> {
-    frag: Vec<u32>,
-    vert: Vec<u32>,
+pub struct Prepare {
+    shadings: Vec<(&'static str, Program)>,
     lights: Vec<Light>,
-    geometry: T,
+    /// Vertices and index already checked by `validate`.
+    geometry: (Vec<Vertex>, Vec<u16>),
 }
 
 pub struct Ready {
-    //light_buf: wgpu::Buffer,
-    //light_count_buf: wgpu::Buffer,
-    projection_buf: wgpu::Buffer,
-    rotation_buf: wgpu::Buffer,
+    /// A buffer for each binding in the layout.
+    uniforms: Vec<(Binding, wgpu::Buffer)>,
+    layout: Layout,
+    /// Named shaders to cycle through and which is in use.
+    shadings: Vec<(&'static str, Program)>,
+    shading: usize,
+    lights: Vec<Light>,
+    geometry: (Vec<Vertex>, Vec<u16>),
     vertex_buf: wgpu::Buffer,
     index_buf: wgpu::Buffer,
     index_len: usize,
     bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
     pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    clear_colour: wgpu::Color,
+    /// Only present when multisampling. Resolved into the swap chain frame.
+    multisampled: Option<(wgpu::Texture, wgpu::TextureView)>,
+    depth: (wgpu::Texture, wgpu::TextureView),
+    bounds: Bounds,
+    /// Changes made between frames. Uploaded at the start of the next render.
+    pending_geometry: bool,
+    pending_lights: Option<Vec<Light>>,
+    pending_shading: bool,
+    /// Last bytes set for each custom uniform of the scene's own shaders. Kept so they can
+    /// be written again after a debug shading replaced the uniform buffers.
+    custom_uniforms: Vec<(u32, Vec<u8>)>,
+    pending_uniforms: bool,
 }
 
 /// Holds all pertinent data and configuration for rendering a scene onto the video device.
@@ -72,273 +341,234 @@ impl Scene<Begin> {
         Scene { state: Begin }
     }
 
-    pub fn shaders<T: CompiledShaders>(self, shaders: &T) -> Scene<Lights> {
-        self.manual_shaders(shaders.vertex(), shaders.fragment())
+    /// Fails if the shaders aren't SPIR-V or their inputs and uniform blocks don't match
+    /// the pipeline's layout.
+    pub fn shaders<T: Pipeline>(self, pipeline: &T) -> Result<Scene<Lights>, Error> {
+        Ok(Scene {
+            state: Lights {
+                shadings: shadings(Program::from_pipeline(pipeline)?)?,
+                lights: Vec::new(),
+            }
+        })
     }
    
-    pub fn manual_shaders(self, vert: &[u8], frag: &[u8]) -> Scene<Lights> {
-        Scene {
+    /// SPIR-V written against the flat shader layout.
+    pub fn manual_shaders(self, vert: &[u8], frag: &[u8]) -> Result<Scene<Lights>, Error> {
+        Ok(Scene {
             state: Lights {
-                frag: wgpu::read_spirv(io::Cursor::new(frag)).unwrap(),
-                vert: wgpu::read_spirv(io::Cursor::new(vert)).unwrap(),
+                shadings: shadings(
+                    Program::new(vert, frag, Layout::flat(), wgpu::CullMode::Back)?
+                )?,
                 lights: Vec::new(),
             }
-        }
+        })
     }
 }
 
 impl Scene<Lights> {
     /// Add a light. Don't add more than `MAX_LIGHTS` as they'll be ignored. If no lights
     /// are added the shape won't be visible.
-    ///
-    /// TODO: Signal to the fragment shader the number of lights loaded.
-    ///       Shader currently assumes exactly two.
     pub fn add_light(mut self, light: Light) -> Self {
         self.state.lights.push(light);
         self
     }
 
-    pub fn geometry<T: Geometry>(self, geometry: T) -> Scene<Prepare<T>> {
+    /// Fails if the geometry isn't a valid indexed triangle list.
+    pub fn geometry<T: Geometry>(self, geometry: T) -> Result<Scene<Prepare>, Error> {
+        let (vertices, index) = geometry.geometry();
+        validate(&vertices, &index)?;
+
         let mut lights = self.state.lights;
         lights.truncate(MAX_LIGHTS);
         lights.shrink_to_fit();
         
         let p = Prepare {
-            frag: self.state.frag,
-            vert: self.state.vert,
+            shadings: self.state.shadings,
             lights,
-            geometry,
+            geometry: (vertices, index),
         };
 
-        Scene { state: p }
+        Ok(Scene { state: p })
     }
 }
 
-impl<T: Geometry> Scene<Prepare<T>> {
+impl Scene<Prepare> {
     pub fn prepare(
-        &self, desc: &wgpu::SwapChainDescriptor, device: &mut wgpu::Device,
-    ) -> Scene<Ready> {
-        let cmd_encoder = device
-            .create_command_encoder(
-                &wgpu::CommandEncoderDescriptor { todo: 0 }
-            );
-        
-        let m_vert = device.create_shader_module(&self.state.vert);
-        let m_frag = device.create_shader_module(&self.state.frag);
-       
-        let projection = Matrix4::zero();
-        let p_ref: &[f32; 16] = projection.as_ref();
-        let projection_buf = device
-            .create_buffer_mapped(
-                16,
-                wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-            )
-            .fill_from_slice(p_ref);
-
-        // Add rotation uniform buffer here (like the projection uniform buffer)
-        let rotation = Matrix4::zero();
-        let r_ref: &[f32; 16] = rotation.as_ref();
-        let rotation_buf = device
-            .create_buffer_mapped(
-                16,
-                wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-            )
-            .fill_from_slice(r_ref);
-
-        let (vertices, index) = self.state.geometry.geometry();
-        
-        let vertex_buf = device
-            .create_buffer_mapped(vertices.len(), wgpu::BufferUsage::VERTEX)
-            .fill_from_slice(&vertices);
-
-        let index_buf = device
-            .create_buffer_mapped(index.len(), wgpu::BufferUsage::INDEX)
-            .fill_from_slice(&index);
-
-        let light_buf_size = (MAX_LIGHTS * LightRaw::sizeof()) as u64;
-        let light_buf_builder = device
-            .create_buffer_mapped(
-                light_buf_size as usize,
-                wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-            );
-        
-        self.state.lights
-            .iter()
-            .take(MAX_LIGHTS)
-            .enumerate()
-            .for_each(|(num, light)| light_buf_builder.data[num] = light.to_raw());
-                    
-        let light_buf = light_buf_builder.finish();
-
-        let light_count = self.state.lights.len() as u32;
-        let light_count_buf = device
-            .create_buffer_mapped(
-                1,
-                wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-            )
-            .fill_from_slice(&[light_count]);
-
-        let bg_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor { bindings: &[
-                // Projection uniform buffer layout
-                wgpu::BindGroupLayoutBinding {
-                    binding: 0,
-                    visibility: wgpu::ShaderStage::VERTEX,
-                    ty: wgpu::BindingType::UniformBuffer { dynamic: false },
-                },
-                
-                // Rotation uniform buffer layout
-                wgpu::BindGroupLayoutBinding {
-                    binding: 1,
-                    visibility: wgpu::ShaderStage::VERTEX,
-                    ty: wgpu::BindingType::UniformBuffer { dynamic: false },
-                },
-                
-                // Lights
-                wgpu::BindGroupLayoutBinding {
-                    binding: 2,
-                    visibility: wgpu::ShaderStage::FRAGMENT,
-                    ty: wgpu::BindingType::UniformBuffer { dynamic: false },
-                },
-
-                // Light Count
-                wgpu::BindGroupLayoutBinding {
-                    binding: 3,
-                    visibility: wgpu::ShaderStage::FRAGMENT,
-                    ty: wgpu::BindingType::UniformBuffer { dynamic: false },
-                },
-            ]}            
-        );
-
-        let pipeline_layout = device.create_pipeline_layout(
-            &wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&bg_layout], }
+        &self, desc: &wgpu::SwapChainDescriptor, target: &Target, device: &mut wgpu::Device,
+    ) -> Result<Scene<Ready>, Error> {
+        let (vertices, index) = &self.state.geometry;
+
+        let positions: Vec<Point3<f32>> = vertices.iter().map(vertex_position).collect();
+        let bounds = Bounds::from_points(&positions);
+
+        let program = &self.state.shadings[0].1;
+        let (vertex_buf, index_buf, index_len) =
+            upload_geometry(device, vertices, index, program.layout.unindexed());
+
+        // Uniforms start zeroed. The lights are copied in with the first frame.
+        let (uniforms, bind_group, pipeline_layout) =
+            bind_uniforms(device, &program.layout);
+
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            program,
+            desc.format,
+            target.sample_count,
         );
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bg_layout,
-            bindings: &[
-                // Projection uniform buffer binding
-                wgpu::Binding {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer {
-                        buffer: &projection_buf,
-                        range: 0..64,
-                    }
-                },
-                
-                // Rotation uniform buffer binding
-                wgpu::Binding {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer {
-                        buffer: &rotation_buf,
-                        range: 0..64
-                    }
-                },
-                
-                // Light uniform buffer binding
-                wgpu::Binding {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer {
-                        buffer: &light_buf,
-                        range: 0..light_buf_size,
-                    }
-                },
-
-                // Light count buffer binding (just a single byte!)
-                wgpu::Binding {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Buffer {
-                        buffer: &light_count_buf,
-                        range: 0..1,
-                    }
-                },
-            ],
-        });
-        
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            layout: &pipeline_layout,
-            vertex_stage: wgpu::ProgrammableStageDescriptor {
-                module: &m_vert,
-                entry_point: "main",
-            },
-            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &m_frag,
-                entry_point: "main",
-            }),
-            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: wgpu::CullMode::Back,
-                depth_bias: 2,
-                depth_bias_slope_scale: 2.0,
-                depth_bias_clamp: 0.0,
-            }),
-            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            color_states: &[wgpu::ColorStateDescriptor {
-                format: desc.format,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                write_mask: wgpu::ColorWrite::ALL,
-            }],
-            depth_stencil_state: None,
-            index_format: wgpu::IndexFormat::Uint16,
-            vertex_buffers: &[wgpu::VertexBufferDescriptor {
-                stride: Vertex::sizeof() as u64,
-                step_mode: wgpu::InputStepMode::Vertex,
-                attributes: &[
-                    // These are the vertexes. Location 0.
-                    wgpu::VertexAttributeDescriptor { 
-                        format: wgpu::VertexFormat::Float3,
-                        offset: 0,
-                        shader_location: 0,
-                    },
-                    
-                    // Our per vertex normal. Location 1.
-                    wgpu::VertexAttributeDescriptor {
-                        format: wgpu::VertexFormat::Float3,
-                        offset: 4 * 3,
-                        shader_location: 1,
-                    },
-                    
-                    // This is the colour. Location 2.
-                    wgpu::VertexAttributeDescriptor { 
-                        format: wgpu::VertexFormat::Float3,
-                        offset: 4 * 6,
-                        shader_location: 2,
-                    },
-                ],
-            }],
-            sample_count: 1,
-            sample_mask: !0,
-            alpha_to_coverage_enabled: false,
-        });
-        
-        let cmd_buf = cmd_encoder.finish();
-        
-        device.get_queue()
-            .submit(&[cmd_buf]);
+        let multisampled = if target.sample_count > 1 {
+            Some(attachment(desc, desc.format, target.sample_count, device))
+        } else {
+            None
+        };
+        let depth = attachment(desc, DEPTH_FORMAT, target.sample_count, device);
 
-        let index_len = index.len();
-        
         let ready = Ready {
-            //light_buf,
-            //light_count_buf,
-            projection_buf,
-            rotation_buf,
+            uniforms,
+            layout: program.layout.clone(),
+            shadings: self.state.shadings.clone(),
+            shading: 0,
+            lights: self.state.lights.clone(),
+            geometry: self.state.geometry.clone(),
             vertex_buf,
             index_buf,
             index_len,
             bind_group,
+            pipeline_layout,
             pipeline,
+            format: desc.format,
+            sample_count: target.sample_count,
+            clear_colour: target.clear_colour,
+            multisampled,
+            depth,
+            bounds,
+            pending_geometry: false,
+            pending_lights: Some(self.state.lights.clone()),
+            pending_shading: false,
+            custom_uniforms: Vec::new(),
+            pending_uniforms: false,
         };
 
-        Scene { state: ready }
+        Ok(Scene { state: ready })
+    }
+}
+
+impl Scene<Ready> {
+    /// Replace the geometry being shown. Takes effect from the next frame. The bounds are
+    /// updated but the camera isn't moved until the view is reset.
+    pub fn set_geometry<T: Geometry>(&mut self, geometry: &T) -> Result<(), Error> {
+        let (vertices, index) = geometry.geometry();
+        validate(&vertices, &index)?;
+
+        let positions: Vec<Point3<f32>> = vertices.iter().map(vertex_position).collect();
+        self.state.bounds = Bounds::from_points(&positions);
+        self.state.geometry = (vertices, index);
+        self.state.pending_geometry = true;
+        Ok(())
+    }
+
+    /// Replace the scene's own shaders and switch to them from any debug shading. The
+    /// pipeline is rebuilt at the start of the next frame. Custom uniforms already set are
+    /// kept where the new layout has a custom uniform at the same binding they fit in.
+    pub fn set_shaders<T: Pipeline>(&mut self, pipeline: &T) -> Result<(), Error> {
+        let program = Program::from_pipeline(pipeline)?;
+        self.state.custom_uniforms.retain(|(binding, bytes)| {
+            let size = program.layout.custom_size(*binding);
+            size.map_or(false, |size| bytes.len() as u64 <= size)
+        });
+        self.state.shadings[0].1 = program;
+        self.state.shading = 0;
+        self.state.pending_shading = true;
+        self.state.pending_uniforms = true;
+        Ok(())
+    }
+
+    /// Name of the shading in use. Either "scene" for the scene's own shaders or one of
+    /// the built in debug shadings.
+    pub fn shading(&self) -> &str {
+        self.state.shadings[self.state.shading].0
+    }
+
+    /// Replace all the lights. Takes effect from the next frame. Lights past `MAX_LIGHTS`
+    /// are ignored.
+    pub fn set_lights(&mut self, mut lights: Vec<Light>) {
+        lights.truncate(MAX_LIGHTS);
+        self.state.pending_lights = Some(lights);
+    }
+
+    /// Copy `bytes` into the custom uniform at `binding` of the scene's own shaders from
+    /// the next frame they're in use. Fails if their layout has no custom uniform there or
+    /// `bytes` doesn't fit. The bytes are kept while cycling through the debug shadings.
+    pub fn set_uniform(&mut self, binding: u32, bytes: Vec<u8>) -> Result<(), Error> {
+        let size = self.state.shadings[0].1.layout
+            .custom_size(binding)
+            .ok_or(Error::Uniform(binding))?;
+
+        if bytes.len() as u64 > size {
+            return Err(Error::Uniform(binding));
+        }
+
+        self.state.custom_uniforms.retain(|(b, _)| *b != binding);
+        self.state.custom_uniforms.push((binding, bytes));
+        self.state.pending_uniforms = true;
+        Ok(())
+    }
+
+    /// The layout of the shaders being used.
+    pub fn layout(&self) -> &Layout {
+        &self.state.layout
+    }
+
+    /// Buffers bound to a uniform of the given kind.
+    fn uniform_bufs<'a>(
+        &'a self, uniform: Uniform,
+    ) -> impl Iterator<Item = &'a wgpu::Buffer> {
+        self.state.uniforms
+            .iter()
+            .filter(move |(binding, _)| binding.uniform == uniform)
+            .map(|(_, buffer)| buffer)
+    }
+
+    /// Copy a staging buffer into every binding of the given kind.
+    fn copy_to_uniform(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        uniform: Uniform,
+        src: &wgpu::Buffer,
+        size: u64,
+    ) {
+        for buffer in self.uniform_bufs(uniform) {
+            encoder.copy_buffer_to_buffer(src, 0, buffer, 0, size);
+        }
     }
 }
 
 impl Renderable for Scene<Ready> {
+    fn bounds(&self) -> &Bounds {
+        &self.state.bounds
+    }
+
+    fn cycle_shading(&mut self) -> Option<&str> {
+        self.state.shading = (self.state.shading + 1) % self.state.shadings.len();
+        self.state.pending_shading = true;
+        Some(self.shading())
+    }
+
+    fn statistics(&self) -> Option<MeshStats> {
+        let (vertices, index) = &self.state.geometry;
+        Some(MeshStats { vertices: vertices.len(), triangles: index.len() / 3 })
+    }
+
+    fn pick(&self, ray: &Ray) -> Option<u32> {
+        let (vertices, index) = &self.state.geometry;
+        let positions: Vec<Point3<f32>> = vertices.iter().map(vertex_position).collect();
+        pick::nearest_face(ray, &positions, index)
+    }
+
     fn render(
         &mut self,
-        projection: &Matrix4<f32>,
+        camera: &Camera<f32>,
         rotation: &Matrix4<f32>,
         frame: &wgpu::SwapChainOutput,
         device: &mut wgpu::Device,
@@ -347,8 +577,98 @@ impl Renderable for Scene<Ready> {
             &wgpu::CommandEncoderDescriptor { todo: 0 }
         );
 
+        // Rebuild the pipeline with any new shaders. A new layout needs new uniforms which
+        // start zeroed, so the lights and custom uniforms are copied in again. Switching
+        // between indexed and unindexed drawing needs the geometry uploaded again.
+        if self.state.pending_shading {
+            self.state.pending_shading = false;
+            let program = &self.state.shadings[self.state.shading].1;
+
+            if program.layout != self.state.layout {
+                let (uniforms, bind_group, pipeline_layout) =
+                    bind_uniforms(device, &program.layout);
+                self.state.uniforms = uniforms;
+                self.state.bind_group = bind_group;
+                self.state.pipeline_layout = pipeline_layout;
+                if program.layout.unindexed() != self.state.layout.unindexed() {
+                    self.state.pending_geometry = true;
+                }
+                self.state.layout = program.layout.clone();
+                if self.state.pending_lights.is_none() {
+                    self.state.pending_lights = Some(self.state.lights.clone());
+                }
+                self.state.pending_uniforms = true;
+            }
+
+            self.state.pipeline = create_pipeline(
+                device,
+                &self.state.pipeline_layout,
+                program,
+                self.state.format,
+                self.state.sample_count,
+            );
+        }
+
+        // Swap in any new geometry. Buffers are recreated as the size may differ.
+        if self.state.pending_geometry {
+            self.state.pending_geometry = false;
+            let (vertices, index) = &self.state.geometry;
+            let (vertex_buf, index_buf, index_len) =
+                upload_geometry(device, vertices, index, self.state.layout.unindexed());
+            self.state.vertex_buf = vertex_buf;
+            self.state.index_buf = index_buf;
+            self.state.index_len = index_len;
+        }
+
+        // Copy in any new lights along with their count
+        if let Some(lights) = self.state.pending_lights.take() {
+            if !lights.is_empty() {
+                let raw: Vec<LightRaw> = lights.iter().map(Light::to_raw).collect();
+                let new_light_buf = device
+                    .create_buffer_mapped(raw.len(), wgpu::BufferUsage::COPY_SRC)
+                    .fill_from_slice(&raw);
+
+                self.copy_to_uniform(
+                    &mut encoder,
+                    Uniform::Lights,
+                    &new_light_buf,
+                    (raw.len() * LightRaw::sizeof()) as u64,
+                );
+            }
+
+            let new_light_count_buf = device
+                .create_buffer_mapped(1, wgpu::BufferUsage::COPY_SRC)
+                .fill_from_slice(&[lights.len() as u32]);
+
+            self.copy_to_uniform(
+                &mut encoder, Uniform::LightCount, &new_light_count_buf, 4,
+            );
+            self.state.lights = lights;
+        }
+
+        // Copy in the custom uniforms when they've changed. Only the scene's own shaders
+        // have them, so they wait while a debug shading is in use.
+        if self.state.pending_uniforms && self.state.shading == 0 {
+            self.state.pending_uniforms = false;
+            for (binding, bytes) in &self.state.custom_uniforms {
+                let new_uniform_buf = device
+                    .create_buffer_mapped(bytes.len(), wgpu::BufferUsage::COPY_SRC)
+                    .fill_from_slice(bytes);
+
+                let buffer = self.state.uniforms
+                    .iter()
+                    .find(|(b, _)| b.binding == *binding);
+                if let Some((_, buffer)) = buffer {
+                    encoder.copy_buffer_to_buffer(
+                        &new_uniform_buf, 0, buffer, 0, bytes.len() as u64
+                    );
+                }
+            }
+        }
+
         // Update with the sent projection
         {
+            let projection = camera.projection();
             let p_ref: &[f32; 16] = projection.as_ref();
             let new_projection_buf = device
                 .create_buffer_mapped(
@@ -357,8 +677,8 @@ impl Renderable for Scene<Ready> {
                 )
                 .fill_from_slice(p_ref);
             
-            encoder.copy_buffer_to_buffer(
-                &new_projection_buf, 0, &self.state.projection_buf, 0, 16 * 4
+            self.copy_to_uniform(
+                &mut encoder, Uniform::Projection, &new_projection_buf, 16 * 4,
             );
         }
 
@@ -372,40 +692,71 @@ impl Renderable for Scene<Ready> {
                 )
                 .fill_from_slice(r_ref);
 
-            encoder.copy_buffer_to_buffer(
-                &new_rotation_buf, 0, &self.state.rotation_buf, 0, 16 * 4
+            self.copy_to_uniform(
+                &mut encoder, Uniform::Rotation, &new_rotation_buf, 16 * 4,
             );
         }
 
-        // Render
+        // And the clip planes
+        {
+            let (near, far) = camera.depth_range();
+            let orthographic = camera.projection_mode() == Projection::Orthographic;
+            let new_clip_buf = device
+                .create_buffer_mapped(3, wgpu::BufferUsage::COPY_SRC)
+                .fill_from_slice(&[near.to_bits(), far.to_bits(), orthographic as u32]);
+
+            self.copy_to_uniform(&mut encoder, Uniform::Clip, &new_clip_buf, 12);
+        }
+
+        // Render. When multisampling draw into the multisampled texture and resolve into
+        // the frame, otherwise straight into the frame.
         {
+            let (attachment, resolve_target) = match &self.state.multisampled {
+                Some((_, view)) => (view, Some(&frame.view)),
+                None => (&frame.view, None),
+            };
+
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
+                    attachment,
+                    resolve_target,
                     load_op: wgpu::LoadOp::Clear,
                     store_op: wgpu::StoreOp::Store,
-                    clear_color: wgpu::Color::BLACK,
+                    clear_color: self.state.clear_colour,
                 }],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(
+                    wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                        attachment: &self.state.depth.1,
+                        depth_load_op: wgpu::LoadOp::Clear,
+                        depth_store_op: wgpu::StoreOp::Store,
+                        stencil_load_op: wgpu::LoadOp::Clear,
+                        stencil_store_op: wgpu::StoreOp::Store,
+                        clear_depth: 1.0,
+                        clear_stencil: 0,
+                    }
+                ),
             });
             rpass.set_pipeline(&self.state.pipeline);
             rpass.set_bind_group(0, &self.state.bind_group, &[]);
-            rpass.set_index_buffer(&self.state.index_buf, 0);
             rpass.set_vertex_buffers(0, &[(&self.state.vertex_buf, 0)]);
-            rpass.draw_indexed(0..self.state.index_len as u32, 0, 0..1);
+            if self.state.layout.unindexed() {
+                rpass.draw(0..self.state.index_len as u32, 0..1);
+            } else {
+                rpass.set_index_buffer(&self.state.index_buf, 0);
+                rpass.draw_indexed(0..self.state.index_len as u32, 0, 0..1);
+            }
         }
 
         device.get_queue().submit(&[encoder.finish()]);
     }
 }
 
-impl<T: Geometry> Initializable for Scene<Prepare<T>> {
+impl Initializable for Scene<Prepare> {
     type Ready = Scene<Ready>;
     
     fn init(
-        self, desc: &wgpu::SwapChainDescriptor, device: &mut wgpu::Device
-    ) -> Self::Ready {
-        self.prepare(desc, device)
+        self, desc: &wgpu::SwapChainDescriptor, target: &Target, device: &mut wgpu::Device
+    ) -> Result<Self::Ready, Error> {
+        self.prepare(desc, target, device)
     }
 }
@@ -1,28 +1,91 @@
 //! Shader handling stuff
+//!
+//! The bundled shaders are embedded as the SPIR-V checked in under `shaders/spirv`, or as
+//! compiled by the build script with the `build-shaders` feature. Compiling GLSL at
+//! runtime needs the `runtime-shaders` feature.
+#[cfg(feature = "runtime-shaders")]
 use std::{fs, path};
 
-use shaderc::{ShaderKind, Error, Compiler};
+#[cfg(feature = "runtime-shaders")]
+use shaderc::{ShaderKind, Compiler};
 
-pub fn load(name: &str, entry: &str, kind: ShaderKind) -> Result<Vec<u8>, Error> {
-    let mut compiler = Compiler::new()
-        .ok_or(Error::NullResultObject("Can't create compiler.".to_owned()))?;
+use crate::Error;
+use crate::pipeline::{Attribute, Layout, Material, Pipeline, Uniform};
+use reflect::Stage;
+
+mod defines;
+pub mod reflect;
+#[cfg(feature = "runtime-shaders")]
+mod preprocess;
+#[cfg(feature = "hot-reload")]
+pub mod reload;
 
-    let filepath = path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .join("shaders")
-        .join(name);
+pub use defines::MAX_LIGHTS;
 
-    let contents = fs::read_to_string(&filepath)
-        .map_err(|e| Error::NullResultObject(format!("{}", &e)))?;
+/// SPIR-V of the built in shaders. Embedded so they work wherever the binary goes.
+const FLAT_VERT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/flat.vert.spv"));
+const FLAT_FRAG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/flat.frag.spv"));
+const NORMALS_VERT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/normals.vert.spv"));
+const NORMALS_FRAG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/normals.frag.spv"));
+const DEPTH_VERT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/depth.vert.spv"));
+const DEPTH_FRAG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/depth.frag.spv"));
+const WIREFRAME_VERT: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/wireframe.vert.spv"));
+const WIREFRAME_FRAG: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/wireframe.frag.spv"));
+const UNLIT_VERT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/unlit.vert.spv"));
+const UNLIT_FRAG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/unlit.frag.spv"));
+const HUD_VERT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/hud.vert.spv"));
+const HUD_FRAG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/hud.frag.spv"));
 
-    let artifact = compiler.compile_into_spirv(&contents, kind, name, entry, None)?;
+/// Compile with includes resolved from `dir` when supplied, then the bundled snippets.
+#[cfg(feature = "runtime-shaders")]
+fn compile_in(
+    dir: Option<&path::Path>, source: &str, name: &str, entry: &str, kind: ShaderKind,
+) -> Result<Vec<u8>, Error> {
+    let mut compiler = Compiler::new().ok_or(Error::CompilerUnavailable)?;
+    let options = preprocess::compile_options(dir).ok_or(Error::CompilerUnavailable)?;
+
+    let artifact = compiler.compile_into_spirv(source, kind, name, entry, Some(&options))?;
     
     Ok(artifact.as_binary_u8().to_owned())
 }
 
+/// Compile GLSL source into SPIR-V. The `name` is only used in error messages. Only the
+/// bundled snippets such as `light.glsl` can be `#include`d. Constants like `MAX_LIGHTS`
+/// are predefined.
+#[cfg(feature = "runtime-shaders")]
+pub fn compile(
+    source: &str, name: &str, entry: &str, kind: ShaderKind,
+) -> Result<Vec<u8>, Error> {
+    compile_in(None, source, name, entry, kind)
+}
+
+/// Load and compile the shader file `name` found in `dir`. Includes are resolved relative
+/// to `dir` before falling back to the bundled snippets.
+#[cfg(feature = "runtime-shaders")]
+pub fn load_from<P: AsRef<path::Path>>(
+    dir: P, name: &str, entry: &str, kind: ShaderKind,
+) -> Result<Vec<u8>, Error> {
+    let contents = fs::read_to_string(dir.as_ref().join(name))?;
+    compile_in(Some(dir.as_ref()), &contents, name, entry, kind)
+}
+
+/// Load and compile a shader from the `shaders` directory of this crate's source. Only
+/// useful when working on this crate as the path is fixed at build time. Use `load_from`
+/// or `compile` everywhere else.
+#[cfg(feature = "runtime-shaders")]
+pub fn load(name: &str, entry: &str, kind: ShaderKind) -> Result<Vec<u8>, Error> {
+    let dir = path::Path::new(env!("CARGO_MANIFEST_DIR")).join("shaders");
+    load_from(dir, name, entry, kind)
+}
+
+#[cfg(feature = "runtime-shaders")]
 pub fn load_vert(name: &str, entry: &str) -> Result<Vec<u8>, Error> {
     load(name, entry, ShaderKind::Vertex)
 }
 
+#[cfg(feature = "runtime-shaders")]
 pub fn load_frag(name: &str, entry: &str) -> Result<Vec<u8>, Error> {
     load(name, entry, ShaderKind::Fragment)
 }
@@ -33,6 +96,52 @@ pub trait CompiledShaders {
     fn vertex(&self) -> &[u8];
 }
 
+/// A vertex and fragment shader pair for whatever rendering the caller has in mind.
+#[derive(Debug, Clone)]
+pub struct Shaders {
+    fragment: Vec<u8>,
+    vertex: Vec<u8>,
+}
+
+impl Shaders {
+    pub fn new(fragment: Vec<u8>, vertex: Vec<u8>) -> Self {
+        Shaders { fragment, vertex }
+    }
+}
+
+impl CompiledShaders for Shaders {
+    fn fragment(&self) -> &[u8] {
+        self.fragment.as_slice()
+    }
+
+    fn vertex(&self) -> &[u8] {
+        self.vertex.as_slice()
+    }
+}
+
+/// Assumed to be written against the flat shader layout. Wrap in a `Material` otherwise.
+impl Pipeline for Shaders {}
+
+/// Compile a pair of GLSL sources. Both must have a `main` entry point.
+#[cfg(feature = "runtime-shaders")]
+pub fn compile_shaders(vert: &str, frag: &str) -> Result<Shaders, Error> {
+    let vert = compile(vert, "vertex", "main", ShaderKind::Vertex)?;
+    let frag = compile(frag, "fragment", "main", ShaderKind::Fragment)?;
+
+    Ok(Shaders::new(frag, vert))
+}
+
+/// Load a pair of GLSL files from `dir`. Both must have a `main` entry point.
+#[cfg(feature = "runtime-shaders")]
+pub fn load_shaders_from<P: AsRef<path::Path>>(
+    dir: P, vert: &str, frag: &str,
+) -> Result<Shaders, Error> {
+    let vert = load_from(dir.as_ref(), vert, "main", ShaderKind::Vertex)?;
+    let frag = load_from(dir.as_ref(), frag, "main", ShaderKind::Fragment)?;
+
+    Ok(Shaders::new(frag, vert))
+}
+
 /// Basic flat shader.
 #[derive(Debug, Clone)]
 pub struct FlatShaders {
@@ -56,9 +165,86 @@ impl CompiledShaders for FlatShaders {
     }
 }
 
-pub fn load_flat_shaders() -> Result<impl CompiledShaders, Error> {
-    let vert = load_vert("flat.vert", "main")?;
-    let frag = load_frag("flat.frag", "main")?;
+impl Pipeline for FlatShaders {}
 
-    Ok(FlatShaders::new(frag, vert))
+/// The built in flat shaders. Needs no files present.
+pub fn load_flat_shaders() -> Result<impl Pipeline, Error> {
+    Ok(FlatShaders::new(FLAT_FRAG.to_owned(), FLAT_VERT.to_owned()))
+}
+
+/// Position at location 0 transformed by the projection and rotation at bindings 0 and 1.
+/// What all the debug shaders start from.
+fn transformed_layout() -> Layout {
+    Layout::new()
+        .attribute(0, Attribute::Position)
+        .uniform(0, Uniform::Projection, &[Stage::Vertex])
+        .uniform(1, Uniform::Rotation, &[Stage::Vertex])
+}
+
+fn embedded(vert: &[u8], frag: &[u8], layout: Layout) -> Material<Shaders> {
+    Material::new(Shaders::new(frag.to_owned(), vert.to_owned()), layout)
+}
+
+/// Normals shown as colour. The x, y and z of the rotated normal map to red, green and
+/// blue. Needs no files present.
+pub fn load_normal_shaders() -> Result<impl Pipeline, Error> {
+    let layout = transformed_layout().attribute(1, Attribute::Normal);
+    Ok(embedded(NORMALS_VERT, NORMALS_FRAG, layout))
+}
+
+/// Distance from the camera linearised between the near and far planes. Near is white and
+/// far is black. Needs no files present.
+pub fn load_depth_shaders() -> Result<impl Pipeline, Error> {
+    let layout = transformed_layout().uniform(2, Uniform::Clip, &[Stage::Fragment]);
+    Ok(embedded(DEPTH_VERT, DEPTH_FRAG, layout))
+}
+
+/// Triangle edges in vertex colour, found from barycentric coordinates. Back faces are
+/// drawn so hidden edges show through. Needs no files present.
+pub fn load_wireframe_shaders() -> Result<impl Pipeline, Error> {
+    let layout = transformed_layout().attribute(2, Attribute::Colour).draw_unindexed();
+    Ok(embedded(WIREFRAME_VERT, WIREFRAME_FRAG, layout).cull_mode(wgpu::CullMode::None))
+}
+
+/// Vertex colour without any lighting. Needs no files present.
+pub fn load_unlit_shaders() -> Result<impl Pipeline, Error> {
+    let layout = transformed_layout().attribute(2, Attribute::Colour);
+    Ok(embedded(UNLIT_VERT, UNLIT_FRAG, layout))
+}
+
+/// Text for the HUD. Sampled from a font atlas rather than fed by a `Layout`, so only the
+/// HUD can use them.
+pub(crate) fn hud_shaders() -> Shaders {
+    Shaders::new(HUD_FRAG.to_owned(), HUD_VERT.to_owned())
+}
+
+#[cfg(all(test, feature = "build-shaders"))]
+mod tests {
+    use std::{fs, path};
+
+    /// Builds without `build-shaders` embed the checked in SPIR-V, so it has to be what the
+    /// build script compiles from the GLSL.
+    #[test]
+    fn checked_in_spirv_matches_the_glsl() {
+        let shader_dir = path::Path::new(env!("CARGO_MANIFEST_DIR")).join("shaders");
+        let out_dir = path::Path::new(env!("OUT_DIR"));
+
+        for entry in fs::read_dir(&shader_dir).unwrap() {
+            let filepath = entry.unwrap().path();
+            let extension = filepath.extension().and_then(|ext| ext.to_str());
+            if !matches!(extension, Some("vert" | "frag")) {
+                continue;
+            }
+
+            let name = filepath.file_name().unwrap().to_str().unwrap();
+            let spv = format!("{}.spv", name);
+            let compiled = fs::read(out_dir.join(&spv)).unwrap();
+            let checked_in = fs::read(shader_dir.join("spirv").join(&spv)).unwrap();
+            assert!(
+                compiled == checked_in,
+                "shaders/spirv/{} doesn't match {}. Copy it over from {}.",
+                spv, name, out_dir.display(),
+            );
+        }
+    }
 }
@@ -0,0 +1,12 @@
+//! Constants shared between Rust and GLSL. These are injected into every shader as macro
+//! definitions so the two sides can't diverge. The build script includes this file too.
+
+/// Most lights a scene will pass on to the shaders.
+pub const MAX_LIGHTS: usize = 10;
+
+/// Macro name and value pairs handed to the shader compiler.
+pub fn definitions() -> Vec<(&'static str, String)> {
+    vec![
+        ("MAX_LIGHTS", MAX_LIGHTS.to_string()),
+    ]
+}
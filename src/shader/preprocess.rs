@@ -0,0 +1,56 @@
+//! Shader preprocessing shared by the build script and runtime compilation. Resolves
+//! `#include` directives and defines the constants from `defines` as macros.
+use std::{fs, path};
+
+use shaderc::{CompileOptions, IncludeType, ResolvedInclude};
+
+use super::defines;
+
+/// GLSL snippets that can be included without any files present.
+pub const SNIPPETS: &[(&str, &str)] = &[
+    ("light.glsl", include_str!("../../shaders/light.glsl")),
+];
+
+/// Compile options with the shared definitions. Includes are looked up in `dir` first when
+/// supplied and then in the bundled snippets.
+pub fn compile_options(dir: Option<&path::Path>) -> Option<CompileOptions<'static>> {
+    let mut options = CompileOptions::new()?;
+
+    for (name, value) in defines::definitions() {
+        options.add_macro_definition(name, Some(&value));
+    }
+
+    let dir = dir.map(path::Path::to_owned);
+    options.set_include_callback(move |requested, include_type, requesting, _depth| {
+        let dir = dir.as_ref().map(path::PathBuf::as_path);
+        resolve(dir, requested, include_type, requesting)
+    });
+
+    Some(options)
+}
+
+fn resolve(
+    dir: Option<&path::Path>, requested: &str, _include_type: IncludeType, requesting: &str,
+) -> Result<ResolvedInclude, String> {
+    if let Some(dir) = dir {
+        let filepath = dir.join(requested);
+        if filepath.is_file() {
+            let content = fs::read_to_string(&filepath)
+                .map_err(|e| format!("Can't read {}: {}", filepath.display(), e))?;
+
+            return Ok(ResolvedInclude {
+                resolved_name: filepath.display().to_string(),
+                content,
+            });
+        }
+    }
+
+    SNIPPETS
+        .iter()
+        .find(|(name, _)| *name == requested)
+        .map(|(name, content)| ResolvedInclude {
+            resolved_name: name.to_string(),
+            content: content.to_string(),
+        })
+        .ok_or_else(|| format!("Can't find {} included from {}.", requested, requesting))
+}
@@ -0,0 +1,598 @@
+//! Just enough SPIR-V reflection to check a shader's interface against what the Rust side
+//! binds. Reads the entry point stage, the located inputs and outputs, and the size of
+//! each uniform block.
+use std::collections::HashMap;
+use std::{error, fmt};
+
+const MAGIC: u32 = 0x0723_0203;
+
+// Opcodes
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+// Decorations
+const DEC_ARRAY_STRIDE: u32 = 6;
+const DEC_MATRIX_STRIDE: u32 = 7;
+const DEC_BUILTIN: u32 = 11;
+const DEC_LOCATION: u32 = 30;
+const DEC_BINDING: u32 = 33;
+const DEC_DESCRIPTOR_SET: u32 = 34;
+const DEC_OFFSET: u32 = 35;
+
+// Storage classes
+const SC_INPUT: u32 = 1;
+const SC_UNIFORM: u32 = 2;
+const SC_OUTPUT: u32 = 3;
+
+// Execution models
+const EM_VERTEX: u32 = 0;
+const EM_FRAGMENT: u32 = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScalarKind {
+    Float,
+    Int,
+}
+
+/// A located input or output of a stage.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Interface {
+    pub location: u32,
+    pub kind: ScalarKind,
+    pub components: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UniformBlock {
+    pub set: u32,
+    pub binding: u32,
+    /// Bytes up to the end of the last member.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reflection {
+    pub stage: Stage,
+    pub inputs: Vec<Interface>,
+    pub outputs: Vec<Interface>,
+    pub uniforms: Vec<UniformBlock>,
+}
+
+/// A uniform buffer the Rust side binds in set 0.
+#[derive(Debug, Copy, Clone)]
+pub struct UniformBinding {
+    pub binding: u32,
+    pub size: u64,
+    pub stages: &'static [Stage],
+}
+
+/// Shader interface doesn't fit what's bound, or the SPIR-V couldn't be understood.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterfaceError {
+    Malformed(String),
+    WrongStage { expected: Stage, found: Stage },
+    MissingVertexAttribute { location: u32 },
+    VertexAttributeKind { location: u32, kind: ScalarKind },
+    VertexAttributeComponents { location: u32, attribute: u32, shader: u32 },
+    MissingStageOutput { location: u32 },
+    UnknownBinding { stage: Stage, set: u32, binding: u32 },
+    BindingStage { stage: Stage, binding: u32 },
+    UniformSize { binding: u32, bound: u64, declared: u64 },
+}
+
+impl fmt::Display for InterfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterfaceError::Malformed(reason) => {
+                write!(f, "Can't reflect SPIR-V. {}", reason)
+            },
+            InterfaceError::WrongStage { expected, found } => write!(
+                f, "Expected a {:?} shader but found a {:?} shader.", expected, found,
+            ),
+            InterfaceError::MissingVertexAttribute { location } => write!(
+                f, "Vertex shader input at location {} has no vertex attribute.", location,
+            ),
+            InterfaceError::VertexAttributeKind { location, kind } => write!(
+                f, "Vertex shader input at location {} is {:?} but the attribute is float.",
+                location, kind,
+            ),
+            InterfaceError::VertexAttributeComponents { location, attribute, shader } => {
+                write!(
+                    f, "Vertex shader input at location {} takes {} floats but is fed {}.",
+                    location, shader, attribute,
+                )
+            },
+            InterfaceError::MissingStageOutput { location } => write!(
+                f,
+                "Fragment shader input at location {} isn't output by the vertex shader.",
+                location,
+            ),
+            InterfaceError::UnknownBinding { stage, set, binding } => write!(
+                f, "{:?} shader uses set {} binding {} which isn't bound.",
+                stage, set, binding,
+            ),
+            InterfaceError::BindingStage { stage, binding } => write!(
+                f, "Binding {} isn't visible to the {:?} shader.", binding, stage,
+            ),
+            InterfaceError::UniformSize { binding, bound, declared } => write!(
+                f, "Uniform block at binding {} is {} bytes but only {} bytes are bound.",
+                binding, declared, bound,
+            ),
+        }
+    }
+}
+
+impl error::Error for InterfaceError {}
+
+/// Types needed to work out interface kinds and block sizes.
+#[derive(Debug, Clone)]
+enum Type {
+    Scalar(ScalarKind, u32),
+    Vector(u32, u32),
+    Matrix(u32, u32),
+    Array(u32, u32),
+    Struct(Vec<u32>),
+    Pointer(u32),
+}
+
+#[derive(Debug, Default)]
+struct Decorations {
+    location: Option<u32>,
+    binding: Option<u32>,
+    set: Option<u32>,
+    array_stride: Option<u32>,
+    builtin: bool,
+}
+
+fn malformed(reason: &str) -> InterfaceError {
+    InterfaceError::Malformed(reason.to_owned())
+}
+
+/// Reflect a single entry point SPIR-V module.
+pub fn reflect(words: &[u32]) -> Result<Reflection, InterfaceError> {
+    if words.len() < 5 || words[0] != MAGIC {
+        return Err(malformed("Missing SPIR-V header."));
+    }
+
+    let mut stage = None;
+    let mut types: HashMap<u32, Type> = HashMap::new();
+    let mut constants: HashMap<u32, u32> = HashMap::new();
+    let mut variables: Vec<(u32, u32, u32)> = Vec::new();
+    let mut decorations: HashMap<u32, Decorations> = HashMap::new();
+    let mut member_offsets: HashMap<(u32, u32), u32> = HashMap::new();
+    let mut member_matrix_strides: HashMap<(u32, u32), u32> = HashMap::new();
+
+    let mut i = 5;
+    while i < words.len() {
+        let count = (words[i] >> 16) as usize;
+        let opcode = words[i] & 0xffff;
+        if count == 0 || i + count > words.len() {
+            return Err(malformed("Instruction runs past the end of the module."));
+        }
+        let ops = &words[i + 1..i + count];
+        let op = |n: usize| {
+            ops.get(n).copied().ok_or_else(|| malformed("Short instruction."))
+        };
+
+        match opcode {
+            OP_ENTRY_POINT => {
+                stage = match op(0)? {
+                    EM_VERTEX => Some(Stage::Vertex),
+                    EM_FRAGMENT => Some(Stage::Fragment),
+                    _ => return Err(malformed("Unsupported execution model.")),
+                };
+            },
+            OP_TYPE_INT => {
+                types.insert(op(0)?, Type::Scalar(ScalarKind::Int, op(1)? / 8));
+            },
+            OP_TYPE_FLOAT => {
+                types.insert(op(0)?, Type::Scalar(ScalarKind::Float, op(1)? / 8));
+            },
+            OP_TYPE_VECTOR => {
+                types.insert(op(0)?, Type::Vector(op(1)?, op(2)?));
+            },
+            OP_TYPE_MATRIX => {
+                types.insert(op(0)?, Type::Matrix(op(1)?, op(2)?));
+            },
+            OP_TYPE_ARRAY => {
+                types.insert(op(0)?, Type::Array(op(1)?, op(2)?));
+            },
+            OP_TYPE_STRUCT => {
+                types.insert(op(0)?, Type::Struct(ops.get(1..).unwrap_or(&[]).to_vec()));
+            },
+            OP_TYPE_POINTER => {
+                types.insert(op(0)?, Type::Pointer(op(2)?));
+            },
+            OP_CONSTANT => {
+                constants.insert(op(1)?, op(2)?);
+            },
+            OP_VARIABLE => {
+                variables.push((op(0)?, op(1)?, op(2)?));
+            },
+            OP_DECORATE => {
+                let entry = decorations.entry(op(0)?).or_default();
+                match op(1)? {
+                    DEC_LOCATION => entry.location = Some(op(2)?),
+                    DEC_BINDING => entry.binding = Some(op(2)?),
+                    DEC_DESCRIPTOR_SET => entry.set = Some(op(2)?),
+                    DEC_ARRAY_STRIDE => entry.array_stride = Some(op(2)?),
+                    DEC_BUILTIN => entry.builtin = true,
+                    _ => (),
+                }
+            },
+            OP_MEMBER_DECORATE => {
+                match op(2)? {
+                    DEC_OFFSET => { member_offsets.insert((op(0)?, op(1)?), op(3)?); },
+                    DEC_MATRIX_STRIDE => {
+                        member_matrix_strides.insert((op(0)?, op(1)?), op(3)?);
+                    },
+                    _ => (),
+                }
+            },
+            _ => (),
+        }
+
+        i += count;
+    }
+
+    let module = Module {
+        types, constants, decorations, member_offsets, member_matrix_strides,
+    };
+    let stage = stage.ok_or_else(|| malformed("No entry point."))?;
+
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    let mut uniforms = Vec::new();
+    for (pointer, id, storage) in variables {
+        let pointee = match module.types.get(&pointer) {
+            Some(Type::Pointer(pointee)) => *pointee,
+            _ => return Err(malformed("Variable isn't a pointer.")),
+        };
+        let decoration = module.decorations.get(&id);
+
+        match storage {
+            SC_INPUT | SC_OUTPUT => {
+                let location = match decoration {
+                    Some(Decorations { builtin: true, .. }) => continue,
+                    Some(Decorations { location: Some(location), .. }) => *location,
+                    _ => continue,
+                };
+                let (kind, components) = module.components(pointee)?;
+                let interface = Interface { location, kind, components };
+                if storage == SC_INPUT {
+                    inputs.push(interface);
+                } else {
+                    outputs.push(interface);
+                }
+            },
+            SC_UNIFORM => {
+                let decoration = decoration
+                    .ok_or_else(|| malformed("Uniform block without a binding."))?;
+                uniforms.push(UniformBlock {
+                    set: decoration.set.unwrap_or(0),
+                    binding: decoration.binding
+                        .ok_or_else(|| malformed("Uniform block without a binding."))?,
+                    size: module.size(pointee, None)?,
+                });
+            },
+            _ => (),
+        }
+    }
+
+    Ok(Reflection { stage, inputs, outputs, uniforms })
+}
+
+struct Module {
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    decorations: HashMap<u32, Decorations>,
+    member_offsets: HashMap<(u32, u32), u32>,
+    member_matrix_strides: HashMap<(u32, u32), u32>,
+}
+
+impl Module {
+    fn get(&self, id: u32) -> Result<&Type, InterfaceError> {
+        self.types.get(&id).ok_or_else(|| malformed("Reference to an unknown type."))
+    }
+
+    /// Scalar kind and number of components of an input or output.
+    fn components(&self, id: u32) -> Result<(ScalarKind, u32), InterfaceError> {
+        match self.get(id)? {
+            Type::Scalar(kind, _) => Ok((*kind, 1)),
+            Type::Vector(component, count) => {
+                let (kind, _) = self.components(*component)?;
+                Ok((kind, *count))
+            },
+            _ => Err(malformed("Only scalar and vector inputs and outputs are supported.")),
+        }
+    }
+
+    /// Size in bytes as laid out in a uniform block. Matrices need the stride from the
+    /// member they belong to.
+    fn size(&self, id: u32, matrix_stride: Option<u32>) -> Result<u64, InterfaceError> {
+        match self.get(id)? {
+            Type::Scalar(_, width) => Ok(*width as u64),
+            Type::Vector(component, count) => {
+                Ok(self.size(*component, None)? * *count as u64)
+            },
+            Type::Matrix(column, count) => match matrix_stride {
+                Some(stride) => Ok(stride as u64 * *count as u64),
+                None => Ok(self.size(*column, None)? * *count as u64),
+            },
+            Type::Array(element, length) => {
+                let length = *self.constants
+                    .get(length)
+                    .ok_or_else(|| malformed("Array length isn't a constant."))?;
+                let stride = match self.decorations.get(&id).and_then(|d| d.array_stride) {
+                    Some(stride) => stride as u64,
+                    None => self.size(*element, matrix_stride)?,
+                };
+                Ok(stride * length as u64)
+            },
+            Type::Struct(members) => {
+                let mut size = 0;
+                for (index, member) in members.iter().enumerate() {
+                    let key = (id, index as u32);
+                    let offset = self.member_offsets.get(&key).copied().unwrap_or(0) as u64;
+                    let stride = self.member_matrix_strides.get(&key).copied();
+                    size = size.max(offset + self.size(*member, stride)?);
+                }
+                Ok(size)
+            },
+            Type::Pointer(..) => Err(malformed("Pointers can't be in a uniform block.")),
+        }
+    }
+}
+
+/// Check a vertex and fragment shader pair against the float vertex attributes, as
+/// locations and component counts, and the uniform buffers bound on the Rust side.
+pub fn validate(
+    vert: &Reflection,
+    frag: &Reflection,
+    attributes: &[(u32, u32)],
+    bindings: &[UniformBinding],
+) -> Result<(), InterfaceError> {
+    if vert.stage != Stage::Vertex {
+        let found = vert.stage;
+        return Err(InterfaceError::WrongStage { expected: Stage::Vertex, found });
+    }
+    if frag.stage != Stage::Fragment {
+        let found = frag.stage;
+        return Err(InterfaceError::WrongStage { expected: Stage::Fragment, found });
+    }
+
+    for input in &vert.inputs {
+        let components = attributes
+            .iter()
+            .find(|(location, _)| *location == input.location)
+            .map(|(_, components)| *components)
+            .ok_or(InterfaceError::MissingVertexAttribute { location: input.location })?;
+        if input.kind != ScalarKind::Float {
+            return Err(InterfaceError::VertexAttributeKind {
+                location: input.location, kind: input.kind,
+            });
+        }
+        if input.components != components {
+            return Err(InterfaceError::VertexAttributeComponents {
+                location: input.location, attribute: components, shader: input.components,
+            });
+        }
+    }
+
+    for input in &frag.inputs {
+        if !vert.outputs.iter().any(|o| o.location == input.location) {
+            return Err(InterfaceError::MissingStageOutput { location: input.location });
+        }
+    }
+
+    for reflection in &[vert, frag] {
+        for block in &reflection.uniforms {
+            let binding = bindings
+                .iter()
+                .find(|b| block.set == 0 && b.binding == block.binding)
+                .ok_or(InterfaceError::UnknownBinding {
+                    stage: reflection.stage, set: block.set, binding: block.binding,
+                })?;
+
+            if !binding.stages.contains(&reflection.stage) {
+                return Err(InterfaceError::BindingStage {
+                    stage: reflection.stage, binding: block.binding,
+                });
+            }
+
+            if block.size > binding.size {
+                return Err(InterfaceError::UniformSize {
+                    binding: block.binding, bound: binding.size, declared: block.size,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::pipeline::{Pipeline, Uniform};
+    use crate::shader::{self, CompiledShaders};
+
+    fn words(bytes: &[u8]) -> Vec<u32> {
+        wgpu::read_spirv(io::Cursor::new(bytes)).unwrap()
+    }
+
+    fn check<T: Pipeline>(pipeline: T) -> Result<(), InterfaceError> {
+        let layout = pipeline.layout();
+        validate(
+            &reflect(&words(pipeline.vertex()))?,
+            &reflect(&words(pipeline.fragment()))?,
+            &layout.locations(),
+            &layout.uniform_bindings(),
+        )
+    }
+
+    fn instruction(opcode: u32, operands: &[u32]) -> Vec<u32> {
+        let mut words = vec![(operands.len() as u32 + 1) << 16 | opcode];
+        words.extend_from_slice(operands);
+        words
+    }
+
+    /// Header and a `main` entry point.
+    fn header(execution_model: u32) -> Vec<u32> {
+        let name = u32::from_le_bytes(*b"main");
+        [
+            vec![MAGIC, 0x0001_0000, 0, 9, 0],
+            instruction(OP_ENTRY_POINT, &[execution_model, 1, name, 0]),
+        ]
+        .concat()
+    }
+
+    /// A vertex shader with a `vec4` input at location 0 and a uniform block holding a
+    /// `vec4` at the binding given.
+    fn vertex(binding: u32) -> Vec<u32> {
+        [
+            header(EM_VERTEX),
+            instruction(OP_DECORATE, &[6, DEC_DESCRIPTOR_SET, 0]),
+            instruction(OP_DECORATE, &[6, DEC_BINDING, binding]),
+            instruction(OP_DECORATE, &[8, DEC_LOCATION, 0]),
+            instruction(OP_MEMBER_DECORATE, &[4, 0, DEC_OFFSET, 0]),
+            instruction(OP_TYPE_FLOAT, &[2, 32]),
+            instruction(OP_TYPE_VECTOR, &[3, 2, 4]),
+            instruction(OP_TYPE_STRUCT, &[4, 3]),
+            instruction(OP_TYPE_POINTER, &[5, SC_UNIFORM, 4]),
+            instruction(OP_VARIABLE, &[5, 6, SC_UNIFORM]),
+            instruction(OP_TYPE_POINTER, &[7, SC_INPUT, 3]),
+            instruction(OP_VARIABLE, &[7, 8, SC_INPUT]),
+        ]
+        .concat()
+    }
+
+    /// A fragment shader with no inputs or uniforms.
+    fn fragment() -> Reflection {
+        reflect(&header(EM_FRAGMENT)).unwrap()
+    }
+
+    fn uniform(binding: u32, size: u64) -> UniformBinding {
+        UniformBinding { binding, size, stages: &[Stage::Vertex, Stage::Fragment] }
+    }
+
+    #[test]
+    fn bundled_shaders_fit_their_layouts() {
+        check(shader::load_flat_shaders().unwrap()).unwrap();
+        check(shader::load_normal_shaders().unwrap()).unwrap();
+        check(shader::load_depth_shaders().unwrap()).unwrap();
+        check(shader::load_wireframe_shaders().unwrap()).unwrap();
+        check(shader::load_unlit_shaders().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn bundled_flat_shaders_are_read() {
+        let flat = shader::load_flat_shaders().unwrap();
+        let vert = reflect(&words(flat.vertex())).unwrap();
+        let frag = reflect(&words(flat.fragment())).unwrap();
+
+        assert_eq!(vert.stage, Stage::Vertex);
+        assert_eq!(frag.stage, Stage::Fragment);
+        let mut locations: Vec<(u32, u32)> = vert.inputs
+            .iter()
+            .map(|input| (input.location, input.components))
+            .collect();
+        locations.sort();
+        assert_eq!(locations, vec![(0, 3), (1, 3), (2, 3)]);
+
+        let mut uniforms: Vec<(u32, u64)> = vert.uniforms
+            .iter()
+            .chain(frag.uniforms.iter())
+            .map(|block| (block.binding, block.size))
+            .collect();
+        uniforms.sort();
+        let lights = Uniform::Lights.size();
+        assert_eq!(uniforms, vec![(0, 64), (1, 64), (2, lights), (3, 4)]);
+    }
+
+    #[test]
+    fn hand_built_module_is_read() {
+        let vert = reflect(&vertex(1)).unwrap();
+        assert_eq!(vert.stage, Stage::Vertex);
+        assert_eq!(
+            vert.inputs,
+            vec![Interface { location: 0, kind: ScalarKind::Float, components: 4 }],
+        );
+        assert_eq!(vert.uniforms, vec![UniformBlock { set: 0, binding: 1, size: 16 }]);
+    }
+
+    #[test]
+    fn bad_magic_is_malformed() {
+        let mut words = vertex(0);
+        words[0] = 0x0203_0723;
+        assert!(matches!(reflect(&words), Err(InterfaceError::Malformed(_))));
+        assert!(matches!(reflect(&[]), Err(InterfaceError::Malformed(_))));
+    }
+
+    #[test]
+    fn truncated_module_is_malformed() {
+        let words = vertex(0);
+        let truncated = reflect(&words[..words.len() - 1]);
+        assert!(matches!(truncated, Err(InterfaceError::Malformed(_))));
+    }
+
+    #[test]
+    fn unknown_binding_is_refused() {
+        let vert = reflect(&vertex(7)).unwrap();
+        assert_eq!(
+            validate(&vert, &fragment(), &[(0, 4)], &[uniform(0, 16)]),
+            Err(InterfaceError::UnknownBinding {
+                stage: Stage::Vertex, set: 0, binding: 7,
+            }),
+        );
+    }
+
+    #[test]
+    fn wrong_stage_is_refused() {
+        assert_eq!(
+            validate(&fragment(), &fragment(), &[(0, 4)], &[uniform(0, 16)]),
+            Err(InterfaceError::WrongStage {
+                expected: Stage::Vertex, found: Stage::Fragment,
+            }),
+        );
+    }
+
+    #[test]
+    fn oversized_uniform_is_refused() {
+        let vert = reflect(&vertex(0)).unwrap();
+        assert_eq!(
+            validate(&vert, &fragment(), &[(0, 4)], &[uniform(0, 8)]),
+            Err(InterfaceError::UniformSize { binding: 0, bound: 8, declared: 16 }),
+        );
+    }
+
+    #[test]
+    fn attribute_components_must_match() {
+        let vert = reflect(&vertex(0)).unwrap();
+        assert_eq!(validate(&vert, &fragment(), &[(0, 4)], &[uniform(0, 16)]), Ok(()));
+        assert_eq!(
+            validate(&vert, &fragment(), &[(0, 3)], &[uniform(0, 16)]),
+            Err(InterfaceError::VertexAttributeComponents {
+                location: 0, attribute: 3, shader: 4,
+            }),
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Recompile shaders as they're edited and swap them into a running scene.
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use log::{info, error};
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use shaderc::ShaderKind;
+
+use crate::Error;
+use crate::scene::{Scene, Ready};
+use crate::presentation::{Application, Control, FrameTime};
+use super::{load_from, Shaders};
+
+/// Watches a shader directory for changes to a vertex and fragment shader pair. Changed
+/// files are recompiled and the last pair that compiled is kept.
+///
+/// Can be passed to `presentation::run_app` as is to hot reload the shaders of a `Scene`.
+pub struct ShaderWatcher {
+    dir: PathBuf,
+    vert: String,
+    frag: String,
+    compiled: Shaders,
+    events: Receiver<DebouncedEvent>,
+    _watcher: RecommendedWatcher,
+}
+
+impl ShaderWatcher {
+    /// Both shaders are compiled up front and must succeed.
+    pub fn new<P: AsRef<Path>>(dir: P, vert: &str, frag: &str) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_owned();
+        let compiled = Shaders::new(
+            load_from(&dir, frag, "main", ShaderKind::Fragment)?,
+            load_from(&dir, vert, "main", ShaderKind::Vertex)?,
+        );
+
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::watcher(tx, Duration::from_millis(250))?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        Ok(ShaderWatcher {
+            dir,
+            vert: vert.to_owned(),
+            frag: frag.to_owned(),
+            compiled,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// The last pair of shaders that compiled.
+    pub fn shaders(&self) -> &Shaders {
+        &self.compiled
+    }
+
+    /// Recompile whatever changed since the last poll. Returns the new pair if anything
+    /// was recompiled. Compile errors are logged and the previous shader is kept.
+    pub fn poll(&mut self) -> Option<&Shaders> {
+        let mut changed = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => changed.push(path),
+                DebouncedEvent::Error(e, _) => error!("Shader watch error: {}", e),
+                _ => (),
+            }
+        }
+
+        let mut reloaded = false;
+        for path in changed {
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            // A changed snippet may be included by either shader so recompile both.
+            let kinds: &[ShaderKind] = if name == self.vert {
+                &[ShaderKind::Vertex]
+            } else if name == self.frag {
+                &[ShaderKind::Fragment]
+            } else if name.ends_with(".glsl") {
+                &[ShaderKind::Vertex, ShaderKind::Fragment]
+            } else {
+                continue;
+            };
+
+            for kind in kinds {
+                reloaded |= self.reload(*kind);
+            }
+        }
+
+        if reloaded {
+            Some(&self.compiled)
+        } else {
+            None
+        }
+    }
+
+    /// Recompile one of the shaders. Returns whether it compiled.
+    fn reload(&mut self, kind: ShaderKind) -> bool {
+        let name = match kind {
+            ShaderKind::Vertex => self.vert.as_str(),
+            _ => self.frag.as_str(),
+        };
+
+        match load_from(&self.dir, name, "main", kind) {
+            Ok(spirv) => {
+                info!("Recompiled {}.", name);
+                self.compiled = match kind {
+                    ShaderKind::Vertex => {
+                        Shaders::new(self.compiled.fragment.clone(), spirv)
+                    },
+                    _ => Shaders::new(spirv, self.compiled.vertex.clone()),
+                };
+                true
+            },
+            Err(e) => {
+                error!("Keeping the last good {}. {}", name, e);
+                false
+            },
+        }
+    }
+}
+
+impl Application<Scene<Ready>> for ShaderWatcher {
+    fn update(
+        &mut self, _time: &FrameTime, scene: &mut Scene<Ready>, _control: &mut Control,
+    ) {
+        if let Some(shaders) = self.poll() {
+            if let Err(e) = scene.set_shaders(shaders) {
+                error!("Keeping the last good pipeline. {}", e);
+            }
+        }
+    }
+}
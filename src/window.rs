@@ -0,0 +1,142 @@
+//! Everything taken from winit's window events. Keys, buttons, cursor positions and wheel
+//! movement are converted into the crate's own types here so input handling doesn't need
+//! winit.
+use winit::dpi::LogicalPosition;
+use winit::event::{
+    ElementState, KeyboardInput, ModifiersState, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent,
+};
+
+use crate::keyboard::{Key, KeyEvent, KeyState, Modifiers};
+use crate::input::{Button, Chord, InputEvent, MouseButton, Position, ScrollDelta};
+
+macro_rules! convert_keys {
+    ($($key:ident),* $(,)?) => {
+        impl From<VirtualKeyCode> for Key {
+            fn from(key: VirtualKeyCode) -> Self {
+                match key {
+                    $(VirtualKeyCode::$key => Key::$key),*
+                }
+            }
+        }
+
+        impl From<Key> for VirtualKeyCode {
+            fn from(key: Key) -> Self {
+                match key {
+                    $(Key::$key => VirtualKeyCode::$key),*
+                }
+            }
+        }
+    }
+}
+
+with_keys!(convert_keys);
+
+impl From<ElementState> for KeyState {
+    fn from(state: ElementState) -> Self {
+        match state {
+            ElementState::Pressed => KeyState::Pressed,
+            ElementState::Released => KeyState::Released,
+        }
+    }
+}
+
+impl From<ModifiersState> for Modifiers {
+    fn from(state: ModifiersState) -> Self {
+        Modifiers { shift: state.shift, ctrl: state.ctrl, alt: state.alt, logo: state.logo }
+    }
+}
+
+impl From<KeyboardInput> for KeyEvent {
+    fn from(input: KeyboardInput) -> Self {
+        KeyEvent {
+            scancode: input.scancode,
+            key: input.virtual_keycode.map(Key::from),
+            state: input.state.into(),
+            modifiers: input.modifiers.into(),
+        }
+    }
+}
+
+impl From<winit::event::MouseButton> for MouseButton {
+    fn from(button: winit::event::MouseButton) -> Self {
+        match button {
+            winit::event::MouseButton::Left => MouseButton::Left,
+            winit::event::MouseButton::Right => MouseButton::Right,
+            winit::event::MouseButton::Middle => MouseButton::Middle,
+            winit::event::MouseButton::Other(number) => MouseButton::Other(number),
+        }
+    }
+}
+
+impl From<LogicalPosition> for Position {
+    fn from(position: LogicalPosition) -> Self {
+        Position::new(position.x, position.y)
+    }
+}
+
+impl From<MouseScrollDelta> for ScrollDelta {
+    fn from(delta: MouseScrollDelta) -> Self {
+        match delta {
+            MouseScrollDelta::LineDelta(x, y) => ScrollDelta::Lines(x, y),
+            MouseScrollDelta::PixelDelta(pixels) => ScrollDelta::Pixels(pixels.x, pixels.y),
+        }
+    }
+}
+
+impl From<VirtualKeyCode> for Button {
+    fn from(key: VirtualKeyCode) -> Self {
+        Button::Key(key.into())
+    }
+}
+
+impl From<winit::event::MouseButton> for Button {
+    fn from(button: winit::event::MouseButton) -> Self {
+        Button::Mouse(button.into())
+    }
+}
+
+impl From<VirtualKeyCode> for Chord {
+    fn from(key: VirtualKeyCode) -> Self {
+        Chord::new(key, Modifiers::NONE)
+    }
+}
+
+impl From<winit::event::MouseButton> for Chord {
+    fn from(button: winit::event::MouseButton) -> Self {
+        Chord::new(button, Modifiers::NONE)
+    }
+}
+
+impl InputEvent {
+    /// The input in a window event if there's any. Keys without a virtual key code are left
+    /// out as they can't be bound.
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match *event {
+            WindowEvent::KeyboardInput { input, .. } => Some(InputEvent::Key {
+                key: input.virtual_keycode?.into(),
+                pressed: input.state == ElementState::Pressed,
+                modifiers: input.modifiers.into(),
+            }),
+            WindowEvent::MouseInput { state, button, modifiers, .. } => {
+                Some(InputEvent::MouseButton {
+                    button: button.into(),
+                    pressed: state == ElementState::Pressed,
+                    modifiers: modifiers.into(),
+                })
+            },
+            WindowEvent::CursorMoved { position, modifiers, .. } => {
+                Some(InputEvent::Cursor {
+                    x: position.x,
+                    y: position.y,
+                    modifiers: modifiers.into(),
+                })
+            },
+            WindowEvent::MouseWheel { delta, modifiers, .. } => {
+                let (x, y) = ScrollDelta::from(delta).lines();
+                Some(InputEvent::Wheel { x, y, modifiers: modifiers.into() })
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Driving input handling with synthetic events, frame by frame, the way the presentation
+//! loop does.
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, FRAC_PI_8};
+use std::time::Duration;
+
+use cgmath::{Deg, Point3, Vector3, Rad};
+
+use shower::input::{
+    self, Action, ActionSet, ActionState, Bindings, HeldInput, InputEvent, Modifiers,
+    Recording,
+};
+use shower::keyboard::{Key, KeyEvent};
+use shower::presentation::{self, Rot, SpinAxis, Turntable, Viewpoint};
+use shower::presentation::camera::{Camera, CameraHandle, Perspective, View};
+
+const FRAME: Duration = Duration::from_millis(250);
+
+fn camera() -> Camera<f32> {
+    Camera::new(
+        Perspective::new(Deg(45f32), 1f32, 1f32, 100f32),
+        View::new(
+            Point3::new(0f32, -4f32, 4f32),
+            Point3::new(0f32, 0f32, 0f32),
+            -Vector3::unit_z(),
+        ),
+    )
+}
+
+fn viewpoint() -> Viewpoint {
+    Viewpoint::new(CameraHandle::new(camera()))
+}
+
+fn key(key: Key, pressed: bool) -> InputEvent {
+    InputEvent::Key { key, pressed, modifiers: Modifiers::NONE }
+}
+
+/// Hold W for two frames, then S as well for a frame, then let go of W.
+fn recording() -> Recording {
+    let mut recording = Recording::new();
+    recording.record(key(Key::W, true));
+    recording.end_frame(FRAME);
+    recording.end_frame(FRAME);
+    recording.record(key(Key::S, true));
+    recording.end_frame(FRAME);
+    recording.record(key(Key::W, false));
+    recording.record(key(Key::Left, true));
+    recording.end_frame(FRAME);
+    recording
+}
+
+#[test]
+fn held_keys_move_the_camera_each_frame() {
+    let bindings = Bindings::default();
+    let mut held = HeldInput::default();
+    let mut state = ActionSet::new();
+
+    input::handle_keyboard(&KeyEvent::pressed(Key::W), &bindings, &mut held, &mut state);
+    input::handle_keyboard(&KeyEvent::pressed(Key::A), &bindings, &mut held, &mut state);
+    let increment = input::frame_increment(&state, &bindings, FRAME);
+    assert_eq!(increment.camera, Vector3::new(0.5, -0.5, 0.0));
+
+    // Opposing keys cancel out along their axis only.
+    input::handle_keyboard(&KeyEvent::pressed(Key::S), &bindings, &mut held, &mut state);
+    let increment = input::frame_increment(&state, &bindings, FRAME);
+    assert_eq!(increment.camera, Vector3::new(0.5, 0.0, 0.0));
+
+    input::handle_keyboard(&KeyEvent::released(Key::W), &bindings, &mut held, &mut state);
+    input::handle_keyboard(&KeyEvent::released(Key::A), &bindings, &mut held, &mut state);
+    let increment = input::frame_increment(&state, &bindings, FRAME);
+    assert_eq!(increment.camera, Vector3::new(0.0, 0.5, 0.0));
+    assert_eq!(increment.x, Rad(0.0));
+}
+
+#[test]
+fn recorded_input_applies_like_live_input() {
+    let bindings = Bindings::default();
+    let mut held = HeldInput::default();
+    let mut state = ActionSet::new();
+
+    for frame in recording().frames() {
+        for event in frame.events.iter() {
+            event.apply(&bindings, &mut held, &mut state);
+        }
+    }
+    assert_eq!(state.held(), vec![Action::CameraMovePY, Action::RotateShapePY]);
+}
+
+#[test]
+fn recordings_survive_being_written_out() {
+    let recording = recording();
+    let read = Recording::from_toml(&recording.to_toml().unwrap()).unwrap();
+    assert_eq!(read, recording);
+}
+
+#[test]
+fn replay_follows_the_same_path_every_time() {
+    let bindings = Bindings::default();
+    let first = presentation::replay(&recording(), &bindings, viewpoint());
+    let read = Recording::from_toml(&recording().to_toml().unwrap()).unwrap();
+    let second = presentation::replay(&read, &bindings, viewpoint());
+
+    assert_eq!(first.len(), 4);
+    assert_eq!(first.len(), second.len());
+    for ((a, a_rot), (b, b_rot)) in first.iter().zip(second.iter()) {
+        assert_eq!(a.view().from(), b.view().from());
+        assert_eq!(a.view().up(), b.view().up());
+        assert_eq!(a_rot, b_rot);
+    }
+
+    // Two frames back along y, one held still by S cancelling W, then one forward while
+    // turning.
+    let from = |i: usize| first[i].0.view().from();
+    assert_eq!(from(0), Point3::new(0.0, -4.5, 4.0));
+    assert_eq!(from(1), Point3::new(0.0, -5.0, 4.0));
+    assert_eq!(from(2), Point3::new(0.0, -5.0, 4.0));
+    assert_eq!(from(3), Point3::new(0.0, -4.5, 4.0));
+    assert_eq!(first[2].1, Rot::default());
+    assert_eq!(first[3].1, Rot::new(Rad(0.0), Deg(22.5).into(), Rad(0.0)));
+}
+
+#[test]
+fn turntable_pauses_for_input() {
+    let turntable = Turntable::new(SpinAxis::Z, Rad(FRAC_PI_2))
+        .resume_after(Duration::from_millis(500));
+    let mut recording = Recording::new();
+    recording.end_frame(FRAME);
+    recording.record(key(Key::W, true));
+    recording.end_frame(FRAME);
+    recording.record(key(Key::W, false));
+    recording.end_frame(FRAME);
+    recording.end_frame(FRAME);
+    recording.record(key(Key::T, true));
+    recording.end_frame(FRAME);
+    recording.end_frame(FRAME);
+
+    let frames = presentation::replay(
+        &recording, &Bindings::default(), viewpoint().turntable(turntable),
+    );
+    let spun = |angle| Rot::new(Rad(0.0), Rad(0.0), Rad(angle));
+
+    // Spins straight away, stops while W is held and until half a second after, then
+    // stops for good once toggled off.
+    assert_eq!(frames[0].1, spun(FRAC_PI_8));
+    assert_eq!(frames[1].1, spun(FRAC_PI_8));
+    assert_eq!(frames[2].1, spun(FRAC_PI_8));
+    assert_eq!(frames[3].1, spun(FRAC_PI_4));
+    assert_eq!(frames[4].1, spun(FRAC_PI_4));
+    assert_eq!(frames[5].1, spun(FRAC_PI_4));
+}
+
+#[test]
+fn reset_view_returns_to_the_refitted_home() {
+    let mut viewpoint = viewpoint();
+    let mut framed = camera();
+    framed.frame(Point3::new(0.0, 0.0, 0.0), 10.0);
+
+    viewpoint.refit_home(10.0, true);
+    assert_eq!(viewpoint.camera().view().from(), Point3::new(0f32, -4f32, 4f32));
+
+    viewpoint.update(Vector3::new(1.0, 0.0, 0.0), Rad(0.0), Rot::default());
+    let view = viewpoint.reset_view();
+    assert_eq!(view.from(), framed.view().from());
+    assert_eq!(viewpoint.camera().depth_range(), framed.depth_range());
+}